@@ -0,0 +1,80 @@
+use std::path::PathBuf;
+use sdl2::{AudioSubsystem, Sdl};
+use sdl2::mixer::{Channel, Chunk, AUDIO_S16LSB, DEFAULT_CHANNELS, MAX_VOLUME};
+
+pub struct Audio {
+    _audio_subsystem: Option<AudioSubsystem>,
+    move_sound: Option<Chunk>,
+    game_over_sound: Option<Chunk>,
+}
+
+impl Audio {
+    fn disabled() -> Audio {
+        Audio {
+            _audio_subsystem: None,
+            move_sound: None,
+            game_over_sound: None
+        }
+    }
+    
+    pub fn new(sdl_context: &Sdl, move_sound: Option<&PathBuf>, game_over_sound: Option<&PathBuf>, volume: f64) -> Audio {
+        if move_sound.is_none() && game_over_sound.is_none() {
+            return Audio::disabled();
+        }
+        
+        let audio_subsystem = match Self::open_audio(sdl_context) {
+            Ok(audio_subsystem) => audio_subsystem,
+            Err(e) => {
+                println!("Failed to initialise audio, sounds are disabled: {}", e);
+                return Audio::disabled();
+            }
+        };
+        
+        let chunk_volume = (volume.clamp(0.0, 1.0) * MAX_VOLUME as f64) as i32;
+        let load_sound = |path: Option<&PathBuf>| {
+            let path = path?;
+            
+            match Chunk::from_file(path) {
+                Ok(mut chunk) => {
+                    chunk.set_volume(chunk_volume);
+                    Some(chunk)
+                },
+                Err(e) => {
+                    println!("Failed to load sound {}: {}", path.display(), e);
+                    None
+                }
+            }
+        };
+        
+        Audio {
+            move_sound: load_sound(move_sound),
+            game_over_sound: load_sound(game_over_sound),
+            _audio_subsystem: Some(audio_subsystem)
+        }
+    }
+    
+    fn open_audio(sdl_context: &Sdl) -> Result<AudioSubsystem, String> {
+        let audio_subsystem = sdl_context.audio()?;
+        
+        sdl2::mixer::open_audio(44_100, AUDIO_S16LSB, DEFAULT_CHANNELS, 1_024)?;
+        sdl2::mixer::allocate_channels(4);
+        
+        Ok(audio_subsystem)
+    }
+    
+    pub fn play_move_sound(&self) {
+        Self::play(&self.move_sound);
+    }
+    
+    pub fn play_game_over_sound(&self) {
+        Self::play(&self.game_over_sound);
+    }
+    
+    fn play(sound: &Option<Chunk>) {
+        if let Some(chunk) = sound {
+            if let Err(e) = Channel::all().play(chunk, 0) {
+                println!("Failed to play sound: {}", e);
+            }
+        }
+    }
+}
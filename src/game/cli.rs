@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use super::AiDifficulty;
+use super::system::{GameSettings, StateBroadcast};
+
+pub const USAGE: &str = "Usage: nim [--heaps N] [--max-stones N] [--difficulty easy|medium|hard] [--position A,B,...] [--daily] [--replay FILE] [--view-replay FILE] [--broadcast stdout|FILE] [--simulate N]";
+
+fn parse_number(flag: &str, value: Option<&String>) -> Result<u32, String> {
+    let value = value.ok_or_else(|| format!("Missing value for {}", flag))?;
+    
+    value.parse::<u32>()
+        .map_err(|_| format!("Invalid number for {}: {}", flag, value))
+}
+
+fn parse_difficulty(value: Option<&String>) -> Result<AiDifficulty, String> {
+    let value = value.ok_or_else(|| "Missing value for --difficulty".to_string())?;
+    
+    match value.to_lowercase().as_str() {
+        "easy" => Ok(AiDifficulty::Easy),
+        "medium" => Ok(AiDifficulty::Medium),
+        "hard" => Ok(AiDifficulty::Hard),
+        _ => Err(format!("Invalid difficulty: {}", value))
+    }
+}
+
+fn parse_position(value: Option<&String>) -> Result<Vec<u32>, String> {
+    let value = value.ok_or_else(|| "Missing value for --position".to_string())?;
+    
+    if value.trim().is_empty() {
+        return Err("Empty position for --position".to_string());
+    }
+    
+    value.split(',')
+        .map(|count| {
+            let count = count.trim();
+            
+            count.parse::<u32>()
+                .map_err(|_| format!("Invalid heap count in --position: {:?}", count))
+        })
+        .collect()
+}
+
+/// The (year, month, day) of a day counted from 1970-01-01, in the
+/// proleptic Gregorian calendar.
+fn date_from_days(days_since_epoch: i64) -> (i64, u32, u32) {
+    let days = days_since_epoch + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = (if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 }) as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    
+    (year, month, day)
+}
+
+/// The date written as YYYYMMDD, so one seed covers one day.
+fn daily_seed(date: (i64, u32, u32)) -> u64 {
+    let (year, month, day) = date;
+    
+    (year * 10_000 + month as i64 * 100 + day as i64) as u64
+}
+
+fn current_date() -> (i64, u32, u32) {
+    let seconds_since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    
+    date_from_days((seconds_since_epoch / 86_400) as i64)
+}
+
+fn validate_position(position: &[u32], max_stones_per_heap: u32) -> Result<(), String> {
+    match position.iter().enumerate().find(|(_, &count)| count > max_stones_per_heap) {
+        Some((heap_index, count)) => Err(format!(
+            "Heap {} in --position has {} stones, more than the maximum of {}",
+            heap_index + 1, count, max_stones_per_heap
+        )),
+        None => Ok(())
+    }
+}
+
+pub fn parse_args(args: &[String], settings: &mut GameSettings) -> Result<(), String> {
+    let mut args_iter = args.iter();
+    
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--heaps" => settings.heaps_count = parse_number(arg, args_iter.next())?,
+            "--max-stones" => settings.max_stones_per_heap = parse_number(arg, args_iter.next())?,
+            "--difficulty" => settings.ai_difficulty = parse_difficulty(args_iter.next())?,
+            "--position" => settings.starting_position = Some(parse_position(args_iter.next())?),
+            "--daily" => settings.seed = Some(daily_seed(current_date())),
+            "--simulate" => settings.simulated_games = Some(parse_number(arg, args_iter.next())?),
+            "--replay" => {
+                let path = args_iter.next().ok_or_else(|| "Missing value for --replay".to_string())?;
+                
+                settings.replay_file = Some(PathBuf::from(path));
+            },
+            "--view-replay" => {
+                let path = args_iter.next().ok_or_else(|| "Missing value for --view-replay".to_string())?;
+                
+                settings.replay_viewer_file = Some(PathBuf::from(path));
+            },
+            "--broadcast" => {
+                let target = args_iter.next().ok_or_else(|| "Missing value for --broadcast".to_string())?;
+                
+                settings.state_broadcast = Some(match target.as_str() {
+                    "stdout" => StateBroadcast::Stdout,
+                    path => StateBroadcast::File(PathBuf::from(path))
+                });
+            },
+            _ => return Err(format!("Unknown argument: {}", arg))
+        }
+    }
+    
+    if let Some(position) = &settings.starting_position {
+        validate_position(position, settings.max_stones_per_heap)?;
+    }
+    
+    Ok(())
+}
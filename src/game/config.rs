@@ -0,0 +1,351 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use sdl2::pixels::Color;
+use serde::Deserialize;
+
+use super::{AiDifficulty, BoardOrientation, HeapGame, MoveLimitTiebreak, NimVariant, WinningMoveTiebreak};
+use super::system::{FramePacing, GameSettings, InputDevice, InputMode, PlayerType, StateBroadcast};
+use super::theme::{Theme, ThemeName};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SettingsFile {
+    window_width: Option<u32>,
+    window_height: Option<u32>,
+    microseconds_per_frame: Option<u64>,
+    target_fps: Option<u32>,
+    idle_microseconds_per_frame: Option<u64>,
+    max_catchup_frames: Option<u32>,
+    frame_pacing: Option<FramePacing>,
+    show_fps: Option<bool>,
+    show_nim_value: Option<bool>,
+    show_starting_outcome: Option<bool>,
+    fps_smoothing: Option<f64>,
+    microseconds_per_ai_move: Option<u64>,
+    instant_ai: Option<bool>,
+    turn_time_limit_ms: Option<u64>,
+    player_one_type: Option<PlayerType>,
+    player_two_type: Option<PlayerType>,
+    players_count: Option<u32>,
+    other_players_type: Option<PlayerType>,
+    player_one_input: Option<InputDevice>,
+    player_two_input: Option<InputDevice>,
+    ai_difficulty: Option<AiDifficulty>,
+    ai_optimal_move_probability: Option<f64>,
+    ai_tiebreak: Option<WinningMoveTiebreak>,
+    release_over_same_heap: Option<bool>,
+    confirm_removal_threshold: Option<u32>,
+    blunder_takebacks: Option<u32>,
+    double_click_time_ms: Option<u64>,
+    heaps_count: Option<u32>,
+    random_heaps_count: Option<(u32, u32)>,
+    max_stones_per_heap: Option<u32>,
+    min_random_stones: Option<u32>,
+    max_random_stones: Option<u32>,
+    target_colour_change_time_ms: Option<u64>,
+    board_orientation: Option<BoardOrientation>,
+    show_empty_slots: Option<bool>,
+    show_heap_indices: Option<bool>,
+    remove_from_bottom: Option<bool>,
+    high_contrast: Option<bool>,
+    per_heap_colours: Option<bool>,
+    input_mode: Option<InputMode>,
+    stone_additions_per_player: Option<u32>,
+    min_stone_length: Option<u32>,
+    theme: Option<ThemeName>,
+    heap_label_colour: Option<(u8, u8, u8)>,
+    variant: Option<NimVariant>,
+    heap_scoring: Option<bool>,
+    max_moves: Option<u32>,
+    move_limit_tiebreak: Option<MoveLimitTiebreak>,
+    history_limit: Option<u32>,
+    max_removal_per_move: Option<u32>,
+    max_heaps_per_move: Option<u32>,
+    heap_games: Option<Vec<HeapGame>>,
+    seed: Option<u64>,
+    state_broadcast: Option<StateBroadcast>,
+    background_texture: Option<PathBuf>,
+    removal_animation_time_ms: Option<u64>,
+    hover_transition_time_ms: Option<u64>,
+    match_target_score: Option<u32>,
+    save_file: Option<PathBuf>,
+    move_log: Option<PathBuf>,
+    move_sound: Option<PathBuf>,
+    game_over_sound: Option<PathBuf>,
+    sound_volume: Option<f64>,
+    font_path: Option<PathBuf>,
+    font_size: Option<u16>,
+    banner_font_size: Option<u16>,
+    label_font_size: Option<u16>,
+}
+
+/// A frame rate of zero is treated as one frame per second.
+fn microseconds_per_frame_for_fps(target_fps: u32) -> u64 {
+    1_000_000 / target_fps.max(1) as u64
+}
+
+impl GameSettings {
+    pub fn from_file(path: &Path) -> Result<GameSettings, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let settings_file: SettingsFile = toml::from_str(&contents)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+        
+        let mut settings = GameSettings::default();
+        settings.apply_settings_file(settings_file);
+        settings.validate()?;
+        
+        Ok(settings)
+    }
+    
+    pub fn validate(&self) -> Result<(), String> {
+        if self.heaps_count < 1 {
+            return Err(format!("heaps_count must be at least 1, got {}", self.heaps_count));
+        }
+        
+        if self.max_stones_per_heap < 1 {
+            return Err(format!("max_stones_per_heap must be at least 1, got {}", self.max_stones_per_heap));
+        }
+        
+        if let Some(range) = &self.random_stones_range {
+            if range.start() > range.end() {
+                return Err(format!("Invalid random stones range {}..={}", range.start(), range.end()));
+            }
+        }
+        
+        if self.players_count < 2 {
+            return Err(format!("players_count must be at least 2, got {}", self.players_count));
+        }
+        
+        if self.max_heaps_per_move < 1 {
+            return Err("max_heaps_per_move must be at least 1".to_string());
+        }
+        
+        if self.history_limit == Some(0) {
+            return Err("history_limit must be at least 1".to_string());
+        }
+        
+        if self.max_removal_per_move == Some(0) {
+            return Err("max_removal_per_move must be at least 1".to_string());
+        }
+        
+        if self.heap_games.contains(&HeapGame::Subtraction(0)) {
+            return Err("A subtraction heap must allow removing at least 1 stone".to_string());
+        }
+        
+        let plain_rules = self.variant == NimVariant::Normal && !self.heap_scoring
+            && self.max_removal_per_move.is_none() && self.max_heaps_per_move == 1;
+        
+        if !self.heap_games.is_empty() && !plain_rules {
+            return Err("heap_games can't be combined with other variants or removal limits".to_string());
+        }
+        
+        Ok(())
+    }
+    
+    fn apply_settings_file(&mut self, settings_file: SettingsFile) {
+        if let Some(window_width) = settings_file.window_width {
+            self.window_width = window_width;
+        }
+        if let Some(window_height) = settings_file.window_height {
+            self.window_height = window_height;
+        }
+        if let Some(microseconds_per_frame) = settings_file.microseconds_per_frame {
+            self.microseconds_per_frame = microseconds_per_frame;
+        }
+        if let Some(target_fps) = settings_file.target_fps {
+            self.microseconds_per_frame = microseconds_per_frame_for_fps(target_fps);
+        }
+        if let Some(idle_microseconds_per_frame) = settings_file.idle_microseconds_per_frame {
+            self.idle_microseconds_per_frame = Some(idle_microseconds_per_frame);
+        }
+        if let Some(max_catchup_frames) = settings_file.max_catchup_frames {
+            self.max_catchup_frames = max_catchup_frames;
+        }
+        if let Some(frame_pacing) = settings_file.frame_pacing {
+            self.frame_pacing = frame_pacing;
+        }
+        if let Some(show_fps) = settings_file.show_fps {
+            self.show_fps = show_fps;
+        }
+        if let Some(show_nim_value) = settings_file.show_nim_value {
+            self.show_nim_value = show_nim_value;
+        }
+        if let Some(show_starting_outcome) = settings_file.show_starting_outcome {
+            self.show_starting_outcome = show_starting_outcome;
+        }
+        if let Some(fps_smoothing) = settings_file.fps_smoothing {
+            self.fps_smoothing = fps_smoothing;
+        }
+        if let Some(microseconds_per_ai_move) = settings_file.microseconds_per_ai_move {
+            self.microseconds_per_ai_move = microseconds_per_ai_move;
+        }
+        if let Some(instant_ai) = settings_file.instant_ai {
+            self.instant_ai = instant_ai;
+        }
+        if let Some(turn_time_limit_ms) = settings_file.turn_time_limit_ms {
+            self.turn_time_limit = Some(Duration::from_millis(turn_time_limit_ms));
+        }
+        if let Some(player_one_type) = settings_file.player_one_type {
+            self.player_one_type = player_one_type;
+        }
+        if let Some(player_two_type) = settings_file.player_two_type {
+            self.player_two_type = player_two_type;
+        }
+        if let Some(players_count) = settings_file.players_count {
+            self.players_count = players_count;
+        }
+        if let Some(other_players_type) = settings_file.other_players_type {
+            self.other_players_type = other_players_type;
+        }
+        if let Some(player_one_input) = settings_file.player_one_input {
+            self.player_one_input = player_one_input;
+        }
+        if let Some(player_two_input) = settings_file.player_two_input {
+            self.player_two_input = player_two_input;
+        }
+        if let Some(ai_difficulty) = settings_file.ai_difficulty {
+            self.ai_difficulty = ai_difficulty;
+        }
+        if let Some(ai_optimal_move_probability) = settings_file.ai_optimal_move_probability {
+            self.ai_optimal_move_probability = ai_optimal_move_probability;
+        }
+        if let Some(ai_tiebreak) = settings_file.ai_tiebreak {
+            self.ai_tiebreak = ai_tiebreak;
+        }
+        if let Some(release_over_same_heap) = settings_file.release_over_same_heap {
+            self.release_over_same_heap = release_over_same_heap;
+        }
+        if settings_file.confirm_removal_threshold.is_some() {
+            self.confirm_removal_threshold = settings_file.confirm_removal_threshold;
+        }
+        if let Some(blunder_takebacks) = settings_file.blunder_takebacks {
+            self.blunder_takebacks = blunder_takebacks;
+        }
+        if let Some(double_click_time_ms) = settings_file.double_click_time_ms {
+            self.double_click_time = Duration::from_millis(double_click_time_ms);
+        }
+        if let Some(heaps_count) = settings_file.heaps_count {
+            self.heaps_count = heaps_count;
+        }
+        if let Some((min_heaps, max_heaps)) = settings_file.random_heaps_count {
+            self.random_heaps_count = Some(min_heaps..=max_heaps);
+        }
+        if let Some(max_stones_per_heap) = settings_file.max_stones_per_heap {
+            self.max_stones_per_heap = max_stones_per_heap;
+        }
+        if settings_file.min_random_stones.is_some() || settings_file.max_random_stones.is_some() {
+            let min_random_stones = settings_file.min_random_stones.unwrap_or(0);
+            let max_random_stones = settings_file.max_random_stones.unwrap_or(u32::MAX);
+            
+            self.random_stones_range = Some(min_random_stones..=max_random_stones);
+        }
+        if let Some(target_colour_change_time_ms) = settings_file.target_colour_change_time_ms {
+            self.target_colour_change_time = Duration::from_millis(target_colour_change_time_ms);
+        }
+        if let Some(board_orientation) = settings_file.board_orientation {
+            self.board_orientation = board_orientation;
+        }
+        if let Some(show_empty_slots) = settings_file.show_empty_slots {
+            self.show_empty_slots = show_empty_slots;
+        }
+        if let Some(show_heap_indices) = settings_file.show_heap_indices {
+            self.show_heap_indices = show_heap_indices;
+        }
+        if let Some(remove_from_bottom) = settings_file.remove_from_bottom {
+            self.remove_from_bottom = remove_from_bottom;
+        }
+        if let Some(high_contrast) = settings_file.high_contrast {
+            self.high_contrast = high_contrast;
+        }
+        if let Some(per_heap_colours) = settings_file.per_heap_colours {
+            self.per_heap_colours = per_heap_colours;
+        }
+        if let Some(input_mode) = settings_file.input_mode {
+            self.input_mode = input_mode;
+        }
+        if let Some(stone_additions_per_player) = settings_file.stone_additions_per_player {
+            self.stone_additions_per_player = stone_additions_per_player;
+        }
+        if let Some(min_stone_length) = settings_file.min_stone_length {
+            self.min_stone_length = min_stone_length;
+        }
+        if let Some(theme) = settings_file.theme {
+            self.theme = Theme::from_name(theme);
+        }
+        if let Some((red, green, blue)) = settings_file.heap_label_colour {
+            self.heap_label_colour = Color::RGB(red, green, blue);
+        }
+        if let Some(variant) = settings_file.variant {
+            self.variant = variant;
+        }
+        if let Some(heap_scoring) = settings_file.heap_scoring {
+            self.heap_scoring = heap_scoring;
+        }
+        if settings_file.max_moves.is_some() {
+            self.max_moves = settings_file.max_moves;
+        }
+        if let Some(move_limit_tiebreak) = settings_file.move_limit_tiebreak {
+            self.move_limit_tiebreak = move_limit_tiebreak;
+        }
+        if settings_file.history_limit.is_some() {
+            self.history_limit = settings_file.history_limit;
+        }
+        if settings_file.max_removal_per_move.is_some() {
+            self.max_removal_per_move = settings_file.max_removal_per_move;
+        }
+        if let Some(max_heaps_per_move) = settings_file.max_heaps_per_move {
+            self.max_heaps_per_move = max_heaps_per_move;
+        }
+        if let Some(heap_games) = settings_file.heap_games {
+            self.heap_games = heap_games;
+        }
+        if settings_file.seed.is_some() {
+            self.seed = settings_file.seed;
+        }
+        if settings_file.state_broadcast.is_some() {
+            self.state_broadcast = settings_file.state_broadcast;
+        }
+        if settings_file.background_texture.is_some() {
+            self.background_texture = settings_file.background_texture;
+        }
+        if let Some(removal_animation_time_ms) = settings_file.removal_animation_time_ms {
+            self.removal_animation_time = Duration::from_millis(removal_animation_time_ms);
+        }
+        if let Some(hover_transition_time_ms) = settings_file.hover_transition_time_ms {
+            self.hover_transition_time = Duration::from_millis(hover_transition_time_ms);
+        }
+        if settings_file.match_target_score.is_some() {
+            self.match_target_score = settings_file.match_target_score;
+        }
+        if let Some(save_file) = settings_file.save_file {
+            self.save_file = save_file;
+        }
+        if settings_file.move_log.is_some() {
+            self.move_log = settings_file.move_log;
+        }
+        if settings_file.move_sound.is_some() {
+            self.move_sound = settings_file.move_sound;
+        }
+        if settings_file.game_over_sound.is_some() {
+            self.game_over_sound = settings_file.game_over_sound;
+        }
+        if let Some(sound_volume) = settings_file.sound_volume {
+            self.sound_volume = sound_volume;
+        }
+        if let Some(font_path) = settings_file.font_path {
+            self.font_path = font_path;
+        }
+        if let Some(font_size) = settings_file.font_size {
+            self.font_size = font_size;
+        }
+        if let Some(banner_font_size) = settings_file.banner_font_size {
+            self.banner_font_size = banner_font_size;
+        }
+        if let Some(label_font_size) = settings_file.label_font_size {
+            self.label_font_size = label_font_size;
+        }
+    }
+}
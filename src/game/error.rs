@@ -0,0 +1,33 @@
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum GameError {
+    Sdl(String),
+    Io { path: PathBuf, source: io::Error },
+    Input(String),
+    Render(String)
+}
+
+impl Display for GameError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            GameError::Sdl(message) => write!(f, "SDL error: {}", message),
+            GameError::Io { path, source } => write!(f, "Failed to open {}: {}", path.display(), source),
+            GameError::Input(message) => write!(f, "Invalid input: {}", message),
+            GameError::Render(message) => write!(f, "Failed to draw a frame: {}", message),
+        }
+    }
+}
+
+impl Error for GameError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GameError::Io { source, .. } => Some(source),
+            _ => None
+        }
+    }
+}
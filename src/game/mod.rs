@@ -1,4 +1,16 @@
+pub mod cli;
 pub mod system;
+mod audio;
+mod config;
+mod error;
 mod nim;
+mod scoreboard;
+mod text;
+mod theme;
 
-pub use nim::{NimGame, NimHeap};
\ No newline at end of file
+pub use nim::{
+    MARGIN_TOP, AiDifficulty, BinaryBreakdown, BoardOrientation, GameResult, HeapGame, MoveError, MoveLimitTiebreak, MoveRecorder,
+    NimCore, NimGame, NimHeap, NimMove, NimVariant, GreedyStrategy, NimStrategy, OptimalStrategy, PositionError,
+    RandomStrategy, RecordedGame, ReplayViewer, StaircaseMove, StaircaseStrategy, SubtractionStrategy, WinningMoveTiebreak,
+    MooreMove, WythoffMove, positions_of_interest
+};
\ No newline at end of file
@@ -0,0 +1,96 @@
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+pub struct LabelledPosition {
+    pub label: &'static str,
+    pub counts: Vec<u32>,
+}
+
+fn nim_sum(counts: &[u32]) -> u32 {
+    counts.iter().fold(0, |acc, count| acc ^ count)
+}
+
+fn random_count(rng: &mut StdRng, max_stones_per_heap: u32) -> u32 {
+    rng.next_u32() % max_stones_per_heap + 1
+}
+
+fn symmetric_position(rng: &mut StdRng, heaps_count: u32, max_stones_per_heap: u32) -> Option<Vec<u32>> {
+    if heaps_count < 2 || !heaps_count.is_multiple_of(2) {
+        return None;
+    }
+    
+    let half = (0..heaps_count / 2)
+        .map(|_| random_count(rng, max_stones_per_heap))
+        .collect::<Vec<u32>>();
+    
+    Some(half.iter().chain(half.iter().rev()).copied().collect())
+}
+
+fn balanced_position(rng: &mut StdRng, heaps_count: u32, max_stones_per_heap: u32) -> Option<Vec<u32>> {
+    if heaps_count < 2 {
+        return None;
+    }
+    
+    let max_attempts = 100;
+    
+    for _ in 0..max_attempts {
+        let mut counts = (0..heaps_count - 1)
+            .map(|_| random_count(rng, max_stones_per_heap))
+            .collect::<Vec<u32>>();
+        let last_count = nim_sum(&counts);
+        
+        if last_count > 0 && last_count <= max_stones_per_heap {
+            counts.push(last_count);
+            return Some(counts);
+        }
+    }
+    
+    None
+}
+
+fn one_move_position(rng: &mut StdRng, heaps_count: u32, max_stones_per_heap: u32) -> Vec<u32> {
+    let mut counts = vec![0; heaps_count as usize];
+    let heap_index = rng.next_u32() % heaps_count;
+    
+    counts[heap_index as usize] = random_count(rng, max_stones_per_heap);
+    
+    counts
+}
+
+pub fn positions_of_interest(heaps_count: u32, max_stones_per_heap: u32, seed: u64) -> Vec<LabelledPosition> {
+    let mut positions = Vec::new();
+    
+    if heaps_count == 0 || max_stones_per_heap == 0 {
+        return positions;
+    }
+    
+    let mut rng = StdRng::seed_from_u64(seed);
+    
+    if heaps_count == 4 && max_stones_per_heap >= 7 {
+        positions.push(LabelledPosition {
+            label: "Classic 1, 3, 5, 7",
+            counts: vec![1, 3, 5, 7],
+        });
+    }
+    
+    if let Some(counts) = symmetric_position(&mut rng, heaps_count, max_stones_per_heap) {
+        positions.push(LabelledPosition {
+            label: "Symmetric: copy your opponent",
+            counts,
+        });
+    }
+    
+    if let Some(counts) = balanced_position(&mut rng, heaps_count, max_stones_per_heap) {
+        positions.push(LabelledPosition {
+            label: "Balanced: nim-sum zero, the player to move loses",
+            counts,
+        });
+    }
+    
+    positions.push(LabelledPosition {
+        label: "One move to win",
+        counts: one_move_position(&mut rng, heaps_count, max_stones_per_heap),
+    });
+    
+    positions
+}
@@ -1,3 +1,15 @@
+mod lessons;
+mod nim_core;
 mod nim_game;
+mod recorder;
+mod replay;
+mod strategy;
 
-pub use nim_game::{NimGame, NimHeap};
\ No newline at end of file
+pub use nim_core::{
+    AiDifficulty, BinaryBreakdown, GameResult, HeapGame, MooreMove, MoveError, MoveLimitTiebreak, NimCore, NimMove, NimVariant, PositionError, StaircaseMove, WinningMoveTiebreak, WythoffMove
+};
+pub use nim_game::{MARGIN_TOP, BoardOrientation, NimGame, NimHeap};
+pub use strategy::{GreedyStrategy, NimStrategy, OptimalStrategy, RandomStrategy, StaircaseStrategy, SubtractionStrategy};
+pub use lessons::positions_of_interest;
+pub use recorder::{MoveRecorder, RecordedGame};
+pub use replay::ReplayViewer;
@@ -0,0 +1,1238 @@
+use std::cmp::{max, min};
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use crate::game::system::Player;
+use super::strategy::{
+    FibonacciStrategy, GameSumStrategy, MooreStrategy, NimStrategy, OptimalStrategy, RandomStrategy, StaircaseStrategy,
+    WythoffStrategy
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NimMove {
+    pub heap_index: usize,
+    pub count_to_remove: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedHeap {
+    size: u32,
+    count: u32,
+}
+
+/// A save without `history` is a bare position: undo is unavailable until
+/// new moves are made on it.
+#[derive(Serialize, Deserialize)]
+struct SavedGame {
+    heaps: Vec<SavedHeap>,
+    player: Player,
+    #[serde(default)]
+    variant: NimVariant,
+    #[serde(default)]
+    history: Vec<MoveRecord>,
+    #[serde(default)]
+    moves_count: u32,
+    #[serde(default)]
+    points: Vec<(Player, u32)>,
+    #[serde(default)]
+    stones_removed: Vec<(Player, u32)>,
+    #[serde(default)]
+    additions_used: Vec<(Player, u32)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MooreMove {
+    pub removals: Vec<NimMove>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WythoffMove {
+    Single(NimMove),
+    Both { count_to_remove: u32 }
+}
+
+/// Moves `count_to_move` stones from step `step_index` one step down;
+/// step 0 is the floor and stones there can't move any more.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StaircaseMove {
+    pub step_index: usize,
+    pub count_to_move: u32,
+}
+
+impl WythoffMove {
+    fn removals(&self) -> Vec<NimMove> {
+        match self {
+            WythoffMove::Single(nim_move) => vec![*nim_move],
+            WythoffMove::Both { count_to_remove } => (0..2)
+                .map(|heap_index| NimMove {
+                    heap_index,
+                    count_to_remove: *count_to_remove
+                })
+                .collect()
+        }
+    }
+}
+
+/// Every heap count written in binary, most significant bit first, together
+/// with the column-wise XOR of those rows, which is the nim-sum.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BinaryBreakdown {
+    pub heap_rows: Vec<Vec<bool>>,
+    pub xor_row: Vec<bool>,
+}
+
+/// One turn in the undo history. An addition keeps the heap's size from
+/// before it, as adding stones may have grown the heap.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum MoveRecord {
+    Removal { removals: Vec<NimMove>, awarded_points: u32 },
+    Addition { heap_index: usize, count: u32, previous_size: u32 },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MoveError {
+    IndexOutOfRange,
+    EmptyRemoval,
+    NotEnoughStones,
+    ExceedsMaxRemoval,
+    TooManyHeaps,
+    DuplicateHeap,
+    FromFloor,
+    NoAdditionsLeft,
+    GameOver
+}
+
+impl Display for MoveError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            MoveError::IndexOutOfRange => write!(f, "Heap index is out of range"),
+            MoveError::EmptyRemoval => write!(f, "At least one stone has to be removed"),
+            MoveError::NotEnoughStones => write!(f, "Heap does not have enough stones"),
+            MoveError::ExceedsMaxRemoval => write!(f, "Too many stones removed in a single move"),
+            MoveError::TooManyHeaps => write!(f, "Too many heaps changed in a single move"),
+            MoveError::DuplicateHeap => write!(f, "The same heap appears twice in one move"),
+            MoveError::FromFloor => write!(f, "Stones on the floor can't be moved"),
+            MoveError::NoAdditionsLeft => write!(f, "No stone additions left"),
+            MoveError::GameOver => write!(f, "The game is already over"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NimVariant {
+    #[default]
+    Normal,
+    Misere,
+    Wythoff,
+    Fibonacci,
+    Staircase
+}
+
+/// The game played on one heap when the board is a sum of different games.
+/// A `Subtraction` heap allows taking at most that many stones per move.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeapGame {
+    #[default]
+    Nim,
+    Subtraction(u32)
+}
+
+impl HeapGame {
+    pub fn max_removal(&self) -> Option<u32> {
+        match self {
+            HeapGame::Nim => None,
+            HeapGame::Subtraction(max_removal) => Some(*max_removal)
+        }
+    }
+
+    /// The Grundy value of a heap of `count` stones in this game: the size of
+    /// the Nim heap it is equivalent to in a sum.
+    pub fn grundy_value(&self, count: u32) -> u32 {
+        match self {
+            HeapGame::Nim => count,
+            HeapGame::Subtraction(max_removal) => count % (max_removal + 1)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AiDifficulty {
+    Easy,
+    Medium,
+    Hard
+}
+
+/// Which of several equally good winning moves the AI plays. `Random`
+/// picks by the position rather than the RNG, so a hint shows the same move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WinningMoveTiebreak {
+    Random,
+    MostStonesRemoved,
+    LowestIndex
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MoveLimitTiebreak {
+    Draw,
+    MostStonesRemoved
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum GameResult {
+    Win(Player),
+    Draw
+}
+
+impl GameResult {
+    fn from_scores(scores: Vec<(Player, u32)>) -> GameResult {
+        let best_score = scores.iter().map(|(_, score)| *score).max().unwrap_or(0);
+        let mut leaders = scores.into_iter().filter(|(_, score)| *score == best_score);
+
+        match (leaders.next(), leaders.next()) {
+            (Some((player, _)), None) => GameResult::Win(player),
+            _ => GameResult::Draw
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PositionError {
+    NoHeaps,
+    TooManyHeaps { heaps_count: usize, max_heaps: usize },
+    CountExceedsSize { heap_index: usize, count: u32, size: u32 }
+}
+
+impl Display for PositionError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            PositionError::NoHeaps => write!(f, "Position has no heaps"),
+            PositionError::TooManyHeaps { heaps_count, max_heaps } =>
+                write!(f, "Position has {} heaps, at most {} are allowed", heaps_count, max_heaps),
+            PositionError::CountExceedsSize { heap_index, count, size } =>
+                write!(f, "Heap {} has {} stones, but holds at most {}", heap_index + 1, count, size),
+        }
+    }
+}
+
+pub struct NimCore {
+    sizes: Vec<u32>,
+    counts: Vec<u32>,
+    player: Player,
+    players_count: u32,
+    variant: NimVariant,
+    ai_difficulty: AiDifficulty,
+    ai_optimal_move_probability: f64,
+    strategy: Box<dyn NimStrategy>,
+    rng: StdRng,
+    heap_scoring: bool,
+    points: HashMap<Player, u32>,
+    moves_count: u32,
+    max_moves: Option<u32>,
+    move_limit_tiebreak: MoveLimitTiebreak,
+    max_removal_per_move: Option<u32>,
+    max_heaps_per_move: u32,
+    heap_games: Vec<HeapGame>,
+    stones_removed: HashMap<Player, u32>,
+    history: Vec<MoveRecord>,
+    history_limit: Option<u32>,
+    redo_stack: Vec<MoveRecord>,
+    additions_per_player: u32,
+    additions_used: HashMap<Player, u32>,
+}
+
+impl NimCore {
+    pub fn new() -> NimCore {
+        NimCore {
+            sizes: Vec::new(),
+            counts: Vec::new(),
+            player: Player::first(),
+            players_count: 2,
+            variant: NimVariant::Normal,
+            ai_difficulty: AiDifficulty::Hard,
+            ai_optimal_move_probability: 1.0,
+            strategy: Box::new(OptimalStrategy::new(NimVariant::Normal, WinningMoveTiebreak::LowestIndex)),
+            rng: StdRng::seed_from_u64(rand::random::<u64>()),
+            heap_scoring: false,
+            points: HashMap::new(),
+            moves_count: 0,
+            max_moves: None,
+            move_limit_tiebreak: MoveLimitTiebreak::Draw,
+            max_removal_per_move: None,
+            max_heaps_per_move: 1,
+            heap_games: Vec::new(),
+            stones_removed: HashMap::new(),
+            history: Vec::new(),
+            history_limit: None,
+            redo_stack: Vec::new(),
+            additions_per_player: 0,
+            additions_used: HashMap::new()
+        }
+    }
+
+    pub fn from_counts(counts: Vec<u32>) -> NimCore {
+        let mut nim_core = NimCore::new();
+
+        nim_core.sizes = counts.clone();
+        nim_core.counts = counts;
+
+        nim_core
+    }
+
+    pub fn from_position(size: u32, counts: &[u32], max_heaps: usize) -> Result<NimCore, PositionError> {
+        if counts.is_empty() {
+            return Err(PositionError::NoHeaps);
+        }
+
+        if counts.len() > max_heaps {
+            return Err(PositionError::TooManyHeaps {
+                heaps_count: counts.len(),
+                max_heaps
+            });
+        }
+
+        if let Some((heap_index, &count)) = counts.iter().enumerate().find(|(_, &count)| count > size) {
+            return Err(PositionError::CountExceedsSize {
+                heap_index,
+                count,
+                size
+            });
+        }
+
+        let mut nim_core = NimCore::new();
+
+        for &count in counts {
+            nim_core.add_heap(size, count);
+        }
+
+        Ok(nim_core)
+    }
+
+    pub fn save_to_file(&self, path: &Path) -> Result<(), String> {
+        let saved_game = SavedGame {
+            heaps: self.sizes.iter().zip(self.counts.iter())
+                .map(|(&size, &count)| SavedHeap { size, count })
+                .collect(),
+            player: self.player.clone(),
+            variant: self.variant,
+            history: self.history.clone(),
+            moves_count: self.moves_count,
+            points: self.points.iter().map(|(player, &points)| (player.clone(), points)).collect(),
+            stones_removed: self.stones_removed.iter().map(|(player, &count)| (player.clone(), count)).collect(),
+            additions_used: self.additions_used.iter().map(|(player, &used)| (player.clone(), used)).collect()
+        };
+
+        let json = serde_json::to_string_pretty(&saved_game)
+            .map_err(|e| e.to_string())?;
+
+        fs::write(path, json)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    pub fn load_from_file(path: &Path) -> Result<NimCore, String> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let saved_game: SavedGame = serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+
+        if saved_game.heaps.is_empty() {
+            return Err(PositionError::NoHeaps.to_string());
+        }
+
+        for (heap_index, saved_heap) in saved_game.heaps.iter().enumerate() {
+            if saved_heap.count > saved_heap.size {
+                return Err(PositionError::CountExceedsSize {
+                    heap_index,
+                    count: saved_heap.count,
+                    size: saved_heap.size
+                }.to_string());
+            }
+        }
+
+        let mut nim_core = NimCore::new();
+
+        for saved_heap in saved_game.heaps.iter() {
+            nim_core.add_heap(saved_heap.size, saved_heap.count);
+        }
+
+        nim_core.variant = saved_game.variant;
+        nim_core.history = saved_game.history;
+
+        if !nim_core.is_history_undoable() || nim_core.history.len() > saved_game.moves_count as usize {
+            return Err(format!("Invalid move history in {}", path.display()));
+        }
+
+        nim_core.player = saved_game.player;
+        nim_core.moves_count = saved_game.moves_count;
+        nim_core.points = saved_game.points.into_iter().collect();
+        nim_core.stones_removed = saved_game.stones_removed.into_iter().collect();
+        nim_core.additions_used = saved_game.additions_used.into_iter().collect();
+
+        Ok(nim_core)
+    }
+
+    /// Moves played under other rules can't be undone under these, so a
+    /// change of variant drops the undo history.
+    pub fn set_variant(&mut self, variant: NimVariant) {
+        if variant != self.variant {
+            self.history.clear();
+            self.redo_stack.clear();
+        }
+
+        self.variant = variant;
+    }
+
+    pub fn get_variant(&self) -> NimVariant {
+        self.variant
+    }
+
+    pub fn set_ai_difficulty(&mut self, ai_difficulty: AiDifficulty, ai_optimal_move_probability: f64) {
+        self.ai_difficulty = ai_difficulty;
+        self.ai_optimal_move_probability = ai_optimal_move_probability.clamp(0.0, 1.0);
+    }
+
+    pub fn set_strategy(&mut self, strategy: Box<dyn NimStrategy>) {
+        self.strategy = strategy;
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn set_heap_scoring(&mut self, heap_scoring: bool) {
+        self.heap_scoring = heap_scoring;
+    }
+
+    pub fn is_heap_scoring(&self) -> bool {
+        self.heap_scoring
+    }
+
+    pub fn get_points(&self, player: &Player) -> u32 {
+        self.points.get(player).copied().unwrap_or(0)
+    }
+
+    pub fn set_move_limit(&mut self, max_moves: Option<u32>, move_limit_tiebreak: MoveLimitTiebreak) {
+        self.max_moves = max_moves;
+        self.move_limit_tiebreak = move_limit_tiebreak;
+    }
+
+    /// Keeps at most `history_limit` moves for undo, dropping the oldest
+    /// ones first; undo stops at the oldest move still kept. The last move
+    /// is always kept, as the Fibonacci limit and the move log read it.
+    pub fn set_history_limit(&mut self, history_limit: Option<u32>) {
+        self.history_limit = history_limit;
+        self.trim_history();
+    }
+
+    fn trim_history(&mut self) {
+        if let Some(history_limit) = self.history_limit {
+            let excess = self.history.len().saturating_sub(history_limit.max(1) as usize);
+
+            self.history.drain(..excess);
+        }
+    }
+
+    pub fn set_max_removal_per_move(&mut self, max_removal_per_move: Option<u32>) {
+        self.max_removal_per_move = max_removal_per_move;
+    }
+
+    fn get_fibonacci_removal_limit(&self) -> Option<u32> {
+        if self.variant != NimVariant::Fibonacci {
+            return None;
+        }
+
+        let last_removals = self.history.iter().rev().find_map(|record| match record {
+            MoveRecord::Removal { removals, .. } => Some(removals),
+            MoveRecord::Addition { .. } => None
+        });
+        let limit = match last_removals {
+            Some(removals) => removals.iter().map(|nim_move| nim_move.count_to_remove).sum::<u32>() * 2,
+            None => self.counts.iter().copied().max().unwrap_or(0).saturating_sub(1).max(1)
+        };
+
+        Some(limit)
+    }
+
+    pub fn get_removal_limit(&self) -> Option<u32> {
+        match (self.max_removal_per_move, self.get_fibonacci_removal_limit()) {
+            (Some(max_removal), Some(fibonacci_limit)) => Some(min(max_removal, fibonacci_limit)),
+            (max_removal, fibonacci_limit) => max_removal.or(fibonacci_limit)
+        }
+    }
+
+    pub fn set_max_heaps_per_move(&mut self, max_heaps_per_move: u32) {
+        self.max_heaps_per_move = max_heaps_per_move.max(1);
+    }
+
+    pub fn get_max_heaps_per_move(&self) -> u32 {
+        self.max_heaps_per_move
+    }
+
+    /// Plays a sum of games, one per heap; heaps past the end of
+    /// `heap_games` are plain Nim heaps. An empty list is ordinary Nim.
+    pub fn set_heap_games(&mut self, heap_games: Vec<HeapGame>) {
+        self.heap_games = heap_games;
+    }
+
+    pub fn get_heap_game(&self, heap_index: usize) -> HeapGame {
+        self.heap_games.get(heap_index).copied().unwrap_or_default()
+    }
+
+    /// The removal limit of the whole game combined with that of the game
+    /// played on the heap.
+    pub fn get_heap_removal_limit(&self, heap_index: usize) -> Option<u32> {
+        match (self.get_removal_limit(), self.get_heap_game(heap_index).max_removal()) {
+            (Some(max_removal), Some(heap_limit)) => Some(min(max_removal, heap_limit)),
+            (max_removal, heap_limit) => max_removal.or(heap_limit)
+        }
+    }
+
+    pub fn get_moves_count(&self) -> u32 {
+        self.moves_count
+    }
+
+    pub fn get_stones_removed(&self, player: &Player) -> u32 {
+        self.stones_removed.get(player).copied().unwrap_or(0)
+    }
+
+    pub fn is_move_limit_reached(&self) -> bool {
+        self.max_moves.is_some_and(|max_moves| self.moves_count >= max_moves)
+    }
+
+    pub fn add_heap(&mut self, size: u32, count: u32) {
+        self.sizes.push(size);
+        self.counts.push(min(size, count));
+    }
+
+    /// Adds a heap with a random number of stones, never leaving a non-empty
+    /// heap size with zero stones.
+    pub fn add_random_heap(&mut self, size: u32, stones_range: Option<&RangeInclusive<u32>>) {
+        let min_count = min(size, 1);
+
+        if let Some(stones_range) = stones_range {
+            let max_stones = max(min(*stones_range.end(), size), min_count);
+            let min_stones = min(max(*stones_range.start(), min_count), max_stones);
+            let count = min_stones + self.rng.next_u32() % (max_stones - min_stones + 1);
+
+            self.add_heap(size, count);
+            return;
+        }
+
+        let half_size = max(size / 2, 1);
+
+        let first_random_count = self.rng.next_u32() % half_size;
+        let second_random_count = self.rng.next_u32() % half_size;
+        let third_random_count = if size.is_multiple_of(2) {
+            0
+        } else {
+            self.rng.next_u32() % 2
+        };
+
+        self.add_heap(size, max(first_random_count + second_random_count + third_random_count, min_count));
+    }
+
+    pub fn remove_last_heap(&mut self) {
+        self.sizes.pop();
+        self.counts.pop();
+    }
+
+    pub fn clear(&mut self) {
+        self.sizes.clear();
+        self.counts.clear();
+        self.player = Player::first();
+        self.points.clear();
+        self.moves_count = 0;
+        self.stones_removed.clear();
+        self.history.clear();
+        self.redo_stack.clear();
+        self.additions_used.clear();
+    }
+
+    pub fn get_heaps_count(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn get_sizes(&self) -> &[u32] {
+        &self.sizes
+    }
+
+    pub fn get_counts(&self) -> &[u32] {
+        &self.counts
+    }
+
+    pub fn get_heap_count(&self, heap_index: usize) -> Option<u32> {
+        self.counts.get(heap_index).copied()
+    }
+
+    pub fn set_players_count(&mut self, players_count: u32) {
+        self.players_count = players_count.max(2);
+
+        if self.player.index() >= self.players_count {
+            self.player = Player::first();
+        }
+    }
+
+    pub fn set_player_to_move(&mut self, player: Player) {
+        self.player = player;
+    }
+
+    pub fn switch_player(&mut self) {
+        self.player = self.player.next(self.players_count);
+    }
+
+    fn switch_to_previous_player(&mut self) {
+        self.player = self.player.previous(self.players_count);
+    }
+
+    fn get_scores<F>(&self, score: F) -> Vec<(Player, u32)>
+    where
+        F: Fn(&Player) -> u32
+    {
+        Player::all(self.players_count)
+            .map(|player| {
+                let player_score = score(&player);
+
+                (player, player_score)
+            })
+            .collect()
+    }
+
+    fn apply_move(&mut self, nim_move: &NimMove) -> Result<(), MoveError> {
+        self.apply_removals(&[*nim_move])
+    }
+
+    fn apply_removals(&mut self, removals: &[NimMove]) -> Result<(), MoveError> {
+        self.play_removals(removals)?;
+        self.redo_stack.clear();
+
+        Ok(())
+    }
+
+    fn validate_removals(&self, removals: &[NimMove]) -> Result<(), MoveError> {
+        if self.is_game_over() {
+            return Err(MoveError::GameOver);
+        }
+
+        if removals.is_empty() {
+            return Err(MoveError::EmptyRemoval);
+        }
+
+        let is_wythoff_diagonal = self.variant == NimVariant::Wythoff
+            && removals.len() == 2
+            && removals[0].count_to_remove == removals[1].count_to_remove;
+
+        if removals.len() > self.max_heaps_per_move as usize && !is_wythoff_diagonal {
+            return Err(MoveError::TooManyHeaps);
+        }
+
+        for (i, nim_move) in removals.iter().enumerate() {
+            if nim_move.heap_index >= self.counts.len() {
+                return Err(MoveError::IndexOutOfRange);
+            }
+
+            if self.variant == NimVariant::Staircase && nim_move.heap_index == 0 {
+                return Err(MoveError::FromFloor);
+            }
+
+            if removals[..i].iter().any(|other| other.heap_index == nim_move.heap_index) {
+                return Err(MoveError::DuplicateHeap);
+            }
+
+            if nim_move.count_to_remove < 1 {
+                return Err(MoveError::EmptyRemoval);
+            }
+
+            if self.get_heap_removal_limit(nim_move.heap_index).is_some_and(|max_removal| nim_move.count_to_remove > max_removal) {
+                return Err(MoveError::ExceedsMaxRemoval);
+            }
+
+            if self.counts[nim_move.heap_index] < nim_move.count_to_remove {
+                return Err(MoveError::NotEnoughStones);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn play_removals(&mut self, removals: &[NimMove]) -> Result<(), MoveError> {
+        self.validate_removals(removals)?;
+
+        let mut awarded_points = 0;
+
+        for nim_move in removals {
+            let count = &mut self.counts[nim_move.heap_index];
+
+            *count -= nim_move.count_to_remove;
+
+            if self.heap_scoring && *count == 0 {
+                awarded_points += 1;
+            }
+
+            if self.variant == NimVariant::Staircase {
+                let step_below = nim_move.heap_index - 1;
+
+                self.counts[step_below] += nim_move.count_to_remove;
+                self.sizes[step_below] = max(self.sizes[step_below], self.counts[step_below]);
+            }
+
+            *self.stones_removed.entry(self.player.clone()).or_insert(0) += nim_move.count_to_remove;
+        }
+
+        if awarded_points > 0 {
+            *self.points.entry(self.player.clone()).or_insert(0) += awarded_points;
+        }
+
+        self.moves_count += 1;
+        self.history.push(MoveRecord::Removal {
+            removals: removals.to_vec(),
+            awarded_points
+        });
+        self.trim_history();
+        self.switch_player();
+
+        Ok(())
+    }
+
+    /// The heap counts from before `record`, or `None` if it can't have been
+    /// played from the current position: a heap would go over its size, or
+    /// a staircase move would come from the floor.
+    fn undone_counts(&self, record: &MoveRecord) -> Option<Vec<u32>> {
+        let mut counts = self.counts.clone();
+
+        match record {
+            MoveRecord::Removal { removals, .. } => {
+                for nim_move in removals.iter() {
+                    let heap_index = nim_move.heap_index;
+                    let restored_count = counts.get(heap_index)?.checked_add(nim_move.count_to_remove)?;
+
+                    if restored_count > self.sizes[heap_index] {
+                        return None;
+                    }
+
+                    counts[heap_index] = restored_count;
+
+                    if self.variant == NimVariant::Staircase {
+                        let step_below = heap_index.checked_sub(1)?;
+
+                        counts[step_below] = counts[step_below].checked_sub(nim_move.count_to_remove)?;
+                    }
+                }
+            },
+            MoveRecord::Addition { heap_index, count, .. } => {
+                counts[*heap_index] = counts.get(*heap_index)?.checked_sub(*count)?;
+            }
+        }
+
+        Some(counts)
+    }
+
+    pub fn undo_last_move(&mut self) -> bool {
+        let counts = match self.history.last().and_then(|record| self.undone_counts(record)) {
+            Some(counts) => counts,
+            None => return false
+        };
+        let record = match self.history.pop() {
+            Some(record) => record,
+            None => return false
+        };
+
+        self.counts = counts;
+        self.switch_to_previous_player();
+
+        match &record {
+            MoveRecord::Removal { removals, awarded_points } => {
+                if let Some(stones_removed) = self.stones_removed.get_mut(&self.player) {
+                    let count_removed = removals.iter().map(|nim_move| nim_move.count_to_remove).sum::<u32>();
+
+                    *stones_removed = stones_removed.saturating_sub(count_removed);
+                }
+
+                if let Some(points) = self.points.get_mut(&self.player) {
+                    *points = points.saturating_sub(*awarded_points);
+                }
+            },
+            MoveRecord::Addition { heap_index, previous_size, .. } => {
+                self.sizes[*heap_index] = max(*previous_size, self.counts[*heap_index]);
+
+                if let Some(additions_used) = self.additions_used.get_mut(&self.player) {
+                    *additions_used = additions_used.saturating_sub(1);
+                }
+            }
+        }
+
+        self.moves_count = self.moves_count.saturating_sub(1);
+        self.redo_stack.push(record);
+
+        true
+    }
+
+    /// Whether every move in the history can be undone, checked by undoing
+    /// them all on a scratch core with the same heaps.
+    fn is_history_undoable(&self) -> bool {
+        let mut scratch_core = NimCore::new();
+
+        scratch_core.sizes = self.sizes.clone();
+        scratch_core.counts = self.counts.clone();
+        scratch_core.variant = self.variant;
+        scratch_core.history = self.history.clone();
+
+        while scratch_core.can_undo() {
+            if !scratch_core.undo_last_move() {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn redo_move(&mut self) -> bool {
+        let record = match self.redo_stack.pop() {
+            Some(record) => record,
+            None => return false
+        };
+
+        match record {
+            MoveRecord::Removal { removals, .. } => self.play_removals(&removals).is_ok(),
+            MoveRecord::Addition { heap_index, count, .. } => self.play_addition(heap_index, count).is_ok()
+        }
+    }
+
+    /// `None` when the last turn was an addition rather than a removal.
+    pub fn get_last_move_removals(&self) -> Option<&[NimMove]> {
+        match self.history.last()? {
+            MoveRecord::Removal { removals, .. } => Some(removals.as_slice()),
+            MoveRecord::Addition { .. } => None
+        }
+    }
+
+    /// The heap and stone count of the last turn if it was an addition.
+    pub fn get_last_addition(&self) -> Option<(usize, u32)> {
+        match self.history.last()? {
+            MoveRecord::Addition { heap_index, count, .. } => Some((*heap_index, *count)),
+            MoveRecord::Removal { .. } => None
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Checks `nim_move` against the current position without playing it.
+    pub fn validate_move(&self, nim_move: &NimMove) -> Result<(), MoveError> {
+        self.validate_removals(&[*nim_move])
+    }
+
+    pub fn is_legal_move(&self, nim_move: &NimMove) -> bool {
+        self.validate_move(nim_move).is_ok()
+    }
+
+    pub fn make_move(&mut self, nim_move: NimMove) -> Result<(), MoveError> {
+        self.apply_move(&nim_move)
+    }
+
+    pub fn make_moore_move(&mut self, moore_move: &MooreMove) -> bool {
+        self.apply_removals(&moore_move.removals).is_ok()
+    }
+
+    pub fn make_wythoff_move(&mut self, wythoff_move: &WythoffMove) -> bool {
+        if self.variant != NimVariant::Wythoff || self.counts.len() != 2 {
+            return false;
+        }
+
+        self.apply_removals(&wythoff_move.removals()).is_ok()
+    }
+
+    pub fn make_staircase_move(&mut self, staircase_move: &StaircaseMove) -> bool {
+        if self.variant != NimVariant::Staircase {
+            return false;
+        }
+
+        self.make_move(NimMove {
+            heap_index: staircase_move.step_index,
+            count_to_remove: staircase_move.count_to_move
+        }).is_ok()
+    }
+
+    pub fn set_additions_per_player(&mut self, additions_per_player: u32) {
+        self.additions_per_player = additions_per_player;
+    }
+
+    pub fn get_additions_per_player(&self) -> u32 {
+        self.additions_per_player
+    }
+
+    pub fn get_additions_left(&self, player: &Player) -> u32 {
+        let used = self.additions_used.get(player).copied().unwrap_or(0);
+
+        self.additions_per_player.saturating_sub(used)
+    }
+
+    /// Poker Nim: puts `count` stones back on a heap as a whole turn, using
+    /// up one of the player's additions. Undone like any other move.
+    pub fn add_stones(&mut self, heap_index: usize, count: u32) -> Result<(), MoveError> {
+        self.play_addition(heap_index, count)?;
+        self.redo_stack.clear();
+
+        Ok(())
+    }
+
+    fn play_addition(&mut self, heap_index: usize, count: u32) -> Result<(), MoveError> {
+        if self.is_game_over() {
+            return Err(MoveError::GameOver);
+        }
+
+        if self.get_additions_left(&self.player) == 0 {
+            return Err(MoveError::NoAdditionsLeft);
+        }
+
+        if heap_index >= self.counts.len() {
+            return Err(MoveError::IndexOutOfRange);
+        }
+
+        if count == 0 {
+            return Err(MoveError::EmptyRemoval);
+        }
+
+        let previous_size = self.sizes[heap_index];
+
+        self.counts[heap_index] += count;
+        self.sizes[heap_index] = max(previous_size, self.counts[heap_index]);
+        *self.additions_used.entry(self.player.clone()).or_insert(0) += 1;
+        self.moves_count += 1;
+        self.history.push(MoveRecord::Addition {
+            heap_index,
+            count,
+            previous_size
+        });
+        self.trim_history();
+        self.switch_player();
+
+        Ok(())
+    }
+
+    pub fn apply_moves(&mut self, moves: &[NimMove]) -> Result<(), (usize, MoveError)> {
+        for (i, nim_move) in moves.iter().enumerate() {
+            self.apply_move(nim_move).map_err(|error| (i, error))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_player_to_move(&self) -> &Player {
+        &self.player
+    }
+
+    pub fn total_stones(&self) -> u32 {
+        self.counts.iter().sum()
+    }
+
+    /// The XOR of the heaps' Grundy values, which are their counts unless
+    /// the board is a sum of different games.
+    pub fn nim_value(&self) -> u32 {
+        self.counts.iter().enumerate()
+            .fold(0, |acc, (heap_index, &count)| acc ^ self.get_heap_game(heap_index).grundy_value(count))
+    }
+
+    pub fn binary_breakdown(&self) -> BinaryBreakdown {
+        let max_count = self.counts.iter().copied().max().unwrap_or(0);
+        let bits_count = max(32 - max_count.leading_zeros(), 1);
+        let to_bits = |value: u32| (0..bits_count).rev()
+            .map(|bit| value & (1 << bit) != 0)
+            .collect::<Vec<bool>>();
+
+        let heap_rows = self.counts.iter()
+            .map(|&count| to_bits(count))
+            .collect::<Vec<Vec<bool>>>();
+        let xor_row = (0..bits_count as usize)
+            .map(|column| heap_rows.iter().filter(|row| row[column]).count() % 2 == 1)
+            .collect::<Vec<bool>>();
+
+        BinaryBreakdown {
+            heap_rows,
+            xor_row
+        }
+    }
+
+    /// In staircase Nim the floor never empties, so only the steps above it count.
+    fn are_all_heaps_empty(&self) -> bool {
+        let first_movable_heap = if self.variant == NimVariant::Staircase { 1 } else { 0 };
+
+        self.counts.iter().skip(first_movable_heap).all(|&count| count == 0)
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.are_all_heaps_empty() || self.is_move_limit_reached()
+    }
+
+    pub fn get_result(&self) -> Option<GameResult> {
+        if !self.is_game_over() {
+            return None;
+        }
+
+        if self.heap_scoring {
+            return Some(GameResult::from_scores(self.get_scores(|player| self.get_points(player))));
+        }
+
+        if self.are_all_heaps_empty() {
+            return match self.variant {
+                NimVariant::Normal | NimVariant::Wythoff | NimVariant::Fibonacci | NimVariant::Staircase => Some(GameResult::Win(self.player.previous(self.players_count))),
+                NimVariant::Misere => Some(GameResult::Win(self.player.clone())),
+            };
+        }
+
+        match self.move_limit_tiebreak {
+            MoveLimitTiebreak::Draw => Some(GameResult::Draw),
+            MoveLimitTiebreak::MostStonesRemoved => Some(GameResult::from_scores(
+                self.get_scores(|player| self.get_stones_removed(player))
+            ))
+        }
+    }
+
+    pub fn prepare_move_to_remaining(&self, heap_index: usize, target_remaining: u32) -> Option<NimMove> {
+        let heap_count = self.get_heap_count(heap_index)?;
+
+        if target_remaining >= heap_count {
+            return None;
+        }
+
+        Some(NimMove {
+            heap_index,
+            count_to_remove: heap_count - target_remaining
+        })
+    }
+
+    /// Plays the game to the end with the AI moving for the first player
+    /// (and every other seat after it) and `opponent` for the rest.
+    pub fn simulate(&mut self, opponent: &dyn NimStrategy, rng: &mut StdRng) -> GameResult {
+        while !self.is_game_over() {
+            let made = if self.player.index().is_multiple_of(2) {
+                self.make_ai_move()
+            } else {
+                opponent.choose_move(&self.counts, rng)
+                    .is_some_and(|nim_move| self.make_move(nim_move).is_ok())
+            };
+
+            if !made {
+                let fallback_made = self.prepare_smallest_move()
+                    .is_some_and(|nim_move| self.make_move(nim_move).is_ok());
+
+                if !fallback_made {
+                    break;
+                }
+            }
+        }
+
+        self.get_result().unwrap_or(GameResult::Draw)
+    }
+
+    pub fn prepare_smallest_move(&self) -> Option<NimMove> {
+        let first_movable_heap = if self.variant == NimVariant::Staircase { 1 } else { 0 };
+        let heap_index = self.counts.iter().enumerate()
+            .skip(first_movable_heap)
+            .position(|(_, &count)| count > 0)? + first_movable_heap;
+
+        Some(NimMove {
+            heap_index,
+            count_to_remove: 1
+        })
+    }
+
+    pub fn is_winning_position(&self) -> bool {
+        self.prepare_hint().is_some()
+    }
+
+    /// Whether the last move gave away a win: the position before it was
+    /// winning for the player who made it, and the next player can now
+    /// force a win. Looks back by undoing and redoing the move.
+    pub fn is_last_move_blunder(&mut self) -> bool {
+        if self.is_game_over() || !self.is_winning_position() || !self.undo_last_move() {
+            return false;
+        }
+
+        let was_winning = self.is_winning_position();
+        self.redo_move();
+
+        was_winning
+    }
+
+    /// The winning move for the current variant as the removals it makes, or
+    /// `None` in a losing position. Dispatches like the AI does and asks the
+    /// same strategies, so a hint never contradicts the optimal AI.
+    pub fn prepare_hint(&self) -> Option<Vec<NimMove>> {
+        if self.variant == NimVariant::Wythoff {
+            return WythoffStrategy.find_winning_move(&self.counts)
+                .map(|wythoff_move| wythoff_move.removals());
+        }
+
+        if self.variant == NimVariant::Staircase {
+            return StaircaseStrategy::new(self.get_removal_limit()).find_winning_move(&self.counts)
+                .map(|staircase_move| vec![NimMove {
+                    heap_index: staircase_move.step_index,
+                    count_to_remove: staircase_move.count_to_move
+                }]);
+        }
+
+        if self.max_heaps_per_move > 1 {
+            return MooreStrategy::new(self.max_heaps_per_move).find_winning_move(&self.counts)
+                .map(|moore_move| moore_move.removals);
+        }
+
+        if !self.heap_games.is_empty() {
+            return GameSumStrategy::new(self.heap_games.clone()).find_winning_move(&self.counts)
+                .map(|nim_move| vec![nim_move]);
+        }
+
+        let winning_move = match self.get_fibonacci_removal_limit() {
+            Some(fibonacci_limit) => FibonacciStrategy::new(fibonacci_limit).find_winning_move(&self.counts),
+            None => self.strategy.find_winning_move(&self.counts)
+        };
+
+        winning_move.map(|nim_move| vec![nim_move])
+    }
+
+    /// Picks and plays the AI's move, dispatching on the variant the same way
+    /// the game loop does.
+    pub fn make_ai_move(&mut self) -> bool {
+        if self.variant == NimVariant::Wythoff {
+            self.prepare_wythoff_ai_move()
+                .is_some_and(|wythoff_move| self.make_wythoff_move(&wythoff_move))
+        } else if self.variant == NimVariant::Staircase {
+            self.prepare_staircase_ai_move()
+                .is_some_and(|staircase_move| self.make_staircase_move(&staircase_move))
+        } else if self.max_heaps_per_move > 1 {
+            self.prepare_moore_ai_move()
+                .is_some_and(|moore_move| self.make_moore_move(&moore_move))
+        } else {
+            self.prepare_ai_move()
+                .is_some_and(|nim_move| self.make_move(nim_move).is_ok())
+        }
+    }
+
+    pub fn prepare_moore_ai_move(&mut self) -> Option<MooreMove> {
+        let moore_strategy = MooreStrategy::new(self.max_heaps_per_move);
+
+        match self.ai_difficulty {
+            AiDifficulty::Easy => moore_strategy.choose_random_move(&self.counts, &mut self.rng),
+            AiDifficulty::Medium => {
+                if (self.rng.next_u32() as f64 / u32::MAX as f64) < self.ai_optimal_move_probability {
+                    moore_strategy.choose_move(&self.counts, &mut self.rng)
+                } else {
+                    moore_strategy.choose_random_move(&self.counts, &mut self.rng)
+                }
+            },
+            AiDifficulty::Hard => moore_strategy.choose_move(&self.counts, &mut self.rng)
+        }
+    }
+
+    pub fn prepare_wythoff_ai_move(&mut self) -> Option<WythoffMove> {
+        let random_move = RandomStrategy::new(None).choose_move(&self.counts, &mut self.rng)
+            .map(WythoffMove::Single);
+
+        match self.ai_difficulty {
+            AiDifficulty::Easy => random_move,
+            AiDifficulty::Medium => {
+                if (self.rng.next_u32() as f64 / u32::MAX as f64) < self.ai_optimal_move_probability {
+                    WythoffStrategy.find_winning_move(&self.counts).or(random_move)
+                } else {
+                    random_move
+                }
+            },
+            AiDifficulty::Hard => WythoffStrategy.find_winning_move(&self.counts).or(random_move)
+        }
+    }
+
+    pub fn prepare_staircase_ai_move(&mut self) -> Option<StaircaseMove> {
+        let staircase_strategy = StaircaseStrategy::new(self.get_removal_limit());
+        let random_move = staircase_strategy.choose_random_move(&self.counts, &mut self.rng);
+
+        match self.ai_difficulty {
+            AiDifficulty::Easy => random_move,
+            AiDifficulty::Medium => {
+                if (self.rng.next_u32() as f64 / u32::MAX as f64) < self.ai_optimal_move_probability {
+                    staircase_strategy.find_winning_move(&self.counts).or(random_move)
+                } else {
+                    random_move
+                }
+            },
+            AiDifficulty::Hard => staircase_strategy.find_winning_move(&self.counts).or(random_move)
+        }
+    }
+
+    pub fn prepare_game_sum_ai_move(&mut self) -> Option<NimMove> {
+        let game_sum_strategy = GameSumStrategy::new(self.heap_games.clone());
+
+        match self.ai_difficulty {
+            AiDifficulty::Easy => game_sum_strategy.choose_random_move(&self.counts, &mut self.rng),
+            AiDifficulty::Medium => {
+                if (self.rng.next_u32() as f64 / u32::MAX as f64) < self.ai_optimal_move_probability {
+                    game_sum_strategy.choose_move(&self.counts, &mut self.rng)
+                } else {
+                    game_sum_strategy.choose_random_move(&self.counts, &mut self.rng)
+                }
+            },
+            AiDifficulty::Hard => game_sum_strategy.choose_move(&self.counts, &mut self.rng)
+        }
+    }
+
+    pub fn prepare_ai_move(&mut self) -> Option<NimMove> {
+        if !self.heap_games.is_empty() {
+            return self.prepare_game_sum_ai_move();
+        }
+
+        let max_removal = self.get_removal_limit();
+        let fibonacci_strategy;
+        let strategy: &dyn NimStrategy = match self.get_fibonacci_removal_limit() {
+            Some(fibonacci_limit) => {
+                fibonacci_strategy = FibonacciStrategy::new(fibonacci_limit);
+                &fibonacci_strategy
+            },
+            None => self.strategy.as_ref()
+        };
+        let random_strategy = RandomStrategy::new(max_removal);
+
+        match self.ai_difficulty {
+            AiDifficulty::Easy => random_strategy.choose_move(&self.counts, &mut self.rng),
+            AiDifficulty::Medium => {
+                if (self.rng.next_u32() as f64 / u32::MAX as f64) < self.ai_optimal_move_probability {
+                    strategy.choose_move(&self.counts, &mut self.rng)
+                } else {
+                    random_strategy.choose_move(&self.counts, &mut self.rng)
+                }
+            },
+            AiDifficulty::Hard => strategy.choose_move(&self.counts, &mut self.rng)
+        }
+    }
+}
+
+impl Default for NimCore {
+    fn default() -> NimCore {
+        NimCore::new()
+    }
+}
+
+impl Display for NimCore {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let counts = self.counts.iter()
+            .map(|count| count.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        let nim_sum = if self.nim_value() == 0 { "zero" } else { "nonzero" };
+
+        write!(f, "{} to move. Heaps: {}. Nim-sum {}.", self.player, counts, nim_sum)
+    }
+}
@@ -1,9 +1,79 @@
-use std::cmp::min;
+use std::cmp::{max, min};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::ops::{Range, RangeInclusive};
+use std::time::Duration;
+use std::path::Path;
+use rand::rngs::StdRng;
+use serde::Deserialize;
 use sdl2::pixels::Color;
 use sdl2::rect::{Point, Rect};
-use sdl2::render::WindowCanvas;
+use sdl2::render::{BlendMode, WindowCanvas};
+use crate::game::error::GameError;
 use crate::game::system::{MouseState, Player};
+use crate::game::text::TextRenderer;
+use crate::game::theme::{step_colour, transition_ratio, Theme};
+use super::recorder::{MoveRecorder, RecordedGame, RecordedTurn};
+use super::strategy::NimStrategy;
+use super::nim_core::{AiDifficulty, BinaryBreakdown, GameResult, HeapGame, MooreMove, MoveError, MoveLimitTiebreak, NimCore, NimMove, NimVariant, StaircaseMove, WythoffMove, PositionError};
 
+pub const MARGIN_TOP: u32 = 100;
+const LABEL_LENGTH: u32 = 30;
+const INDEX_LABEL_GAP: i32 = 10;
+const MIN_BOARD_WIDTH: u32 = 200;
+const MIN_BOARD_HEIGHT: u32 = 200;
+
+fn clamp_length(length: f64) -> u32 {
+    length.max(1.0) as u32
+}
+
+/// The minimum stone length gives way when a heap would overflow its area,
+/// so every stone keeps at least a pixel to click.
+fn fit_stone_length(stone_length: f64, area_length: u32, size: u32) -> u32 {
+    let fitting_length = area_length / size.max(1);
+    
+    clamp_length(stone_length).min(fitting_length).max(1)
+}
+
+fn animation_progress(elapsed: Duration, total: Duration) -> f64 {
+    if total.is_zero() {
+        return 1.0;
+    }
+    
+    (elapsed.as_secs_f64() / total.as_secs_f64()).clamp(0.0, 1.0)
+}
+
+fn drag_removal_count(drag_length: u32, stone_length: u32, count: u32, max_removal: Option<u32>) -> u32 {
+    let dragged_stones = drag_length.div_ceil(stone_length.max(1));
+    
+    min(dragged_stones, max_removal.map_or(count, |max_removal| min(count, max_removal)))
+}
+
+/// Stones alternate between a bright and a dark fill so neighbours stay
+/// distinguishable; stones about to be removed use yellow instead of grey.
+fn high_contrast_stone_colour(stone_index: u32, to_remove: bool) -> Color {
+    match (to_remove, stone_index.is_multiple_of(2)) {
+        (true, true) => Color::RGB(255, 230, 0),
+        (true, false) => Color::RGB(190, 150, 0),
+        (false, true) => Color::RGB(245, 245, 245),
+        (false, false) => Color::RGB(120, 120, 120)
+    }
+}
+
+struct RemovalAnimation {
+    stone_rects: Vec<Rect>,
+    elapsed: Duration,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BoardOrientation {
+    #[default]
+    Vertical,
+    Horizontal
+}
+
+#[derive(Clone)]
 pub struct NimHeap {
     size: u32,
     count: u32,
@@ -12,6 +82,10 @@ pub struct NimHeap {
     stone_width: u32,
     stone_height: u32,
     area_rectangle: Rect,
+    orientation: BoardOrientation,
+    max_removal: Option<u32>,
+    remove_from_bottom: bool,
+    stone_colour: Option<Color>,
 }
 
 impl NimHeap {
@@ -24,29 +98,72 @@ impl NimHeap {
             stone_width: 1,
             stone_height: 1,
             area_rectangle: Rect::new(0, 0, 1, 1),
+            orientation: BoardOrientation::default(),
+            max_removal: None,
+            remove_from_bottom: false,
+            stone_colour: None,
         }
     }
 
+    /// Stone 0 is the first one a move takes, next to the empty slots. By
+    /// default the stones sit at the far end of the heap and go from the
+    /// near end; `remove_from_bottom` flips that.
     fn get_nth_stone_rect(&self, n: usize) -> Rect {
-        let empty_slots_count = self.size - self.count;
+        if self.remove_from_bottom {
+            return self.get_slot_rect(self.count - 1 - n as u32);
+        }
         
-        let x = self.corner_x;
-        let y = self.corner_y + ((empty_slots_count + n as u32) * self.stone_height) as i32;
+        let empty_slots_count = self.size - self.count;
         
-        Rect::new(x, y, self.stone_width, self.stone_height)
+        self.get_slot_rect(empty_slots_count + n as u32)
+    }
+    
+    fn get_empty_slots(&self) -> Range<u32> {
+        if self.remove_from_bottom {
+            self.count..self.size
+        } else {
+            0..(self.size - self.count)
+        }
+    }
+    
+    fn get_slot_rect(&self, slot: u32) -> Rect {
+        match self.orientation {
+            BoardOrientation::Vertical => {
+                let x = self.corner_x;
+                let y = self.corner_y + (slot * self.stone_height) as i32;
+                
+                Rect::new(x, y, self.stone_width, self.stone_height)
+            },
+            BoardOrientation::Horizontal => {
+                let x = self.corner_x + ((self.size - 1 - slot) * self.stone_width) as i32;
+                let y = self.corner_y;
+                
+                Rect::new(x, y, self.stone_width, self.stone_height)
+            }
+        }
     }
 
-    fn set_heap_sizes(&mut self, area_rectangle: Rect, stone_height: f64) {
+    fn set_heap_sizes(&mut self, area_rectangle: Rect, stone_length: f64, orientation: BoardOrientation) {
         self.corner_x = area_rectangle.x();
         self.corner_y = area_rectangle.y();
-        self.stone_width = area_rectangle.width();
-        self.stone_height = stone_height as u32;
         self.area_rectangle = area_rectangle;
+        self.orientation = orientation;
         
-        let last_y = self.corner_y + self.size as i32 * self.stone_height as i32;
-        let difference = self.area_rectangle.y + self.area_rectangle.height() as i32 - last_y;
-        
-        self.corner_y += difference;
+        match orientation {
+            BoardOrientation::Vertical => {
+                self.stone_width = area_rectangle.width();
+                self.stone_height = fit_stone_length(stone_length, area_rectangle.height(), self.size);
+                
+                let last_y = self.corner_y + self.size as i32 * self.stone_height as i32;
+                let difference = self.area_rectangle.y + self.area_rectangle.height() as i32 - last_y;
+                
+                self.corner_y += difference;
+            },
+            BoardOrientation::Horizontal => {
+                self.stone_width = fit_stone_length(stone_length, area_rectangle.width(), self.size);
+                self.stone_height = area_rectangle.height();
+            }
+        }
     }
     
     pub fn get_count(&self) -> u32 {
@@ -69,7 +186,10 @@ impl NimHeap {
             return None;
         }
         
-        let count_to_remove = self.count - new_count;
+        let count_to_remove = match self.max_removal {
+            Some(max_removal) => min(self.count - new_count, max_removal),
+            None => self.count - new_count
+        };
         
         Some(NimMove {
             heap_index,
@@ -77,133 +197,731 @@ impl NimHeap {
         })
     }
 
-    fn draw(&self, canvas: &mut WindowCanvas, mouse_state: &MouseState) -> Result<(), String> {
-        let mouse_point = mouse_state.point;
+    /// Removes one stone for every stone length dragged along the heap, in
+    /// either direction, up to the whole heap.
+    fn prepare_drag_move(&self, heap_index: usize, press_point: Point, point: Point) -> Option<NimMove> {
+        let (drag_length, stone_length) = match self.orientation {
+            BoardOrientation::Vertical => (point.y() - press_point.y(), self.stone_height),
+            BoardOrientation::Horizontal => (point.x() - press_point.x(), self.stone_width)
+        };
+        
+        let count_to_remove = drag_removal_count(drag_length.unsigned_abs(), stone_length, self.count, self.max_removal);
+        
+        if count_to_remove == 0 {
+            return None;
+        }
+        
+        Some(NimMove {
+            heap_index,
+            count_to_remove,
+        })
+    }
+
+    fn prepare_single_stone_move(&self, heap_index: usize, point: Point) -> Option<NimMove> {
+        if self.count == 0 || !self.area_rectangle.contains_point(point) {
+            return None;
+        }
+        
+        Some(NimMove {
+            heap_index,
+            count_to_remove: 1,
+        })
+    }
+
+    fn get_label_area(&self) -> Rect {
+        let area = self.area_rectangle;
+        
+        match self.orientation {
+            BoardOrientation::Vertical =>
+                Rect::new(area.x(), area.bottom(), area.width(), LABEL_LENGTH),
+            BoardOrientation::Horizontal =>
+                Rect::new(area.x() - LABEL_LENGTH as i32, area.y(), LABEL_LENGTH, area.height()),
+        }
+    }
+    
+    /// Above a vertical heap, inside the top margin, and right of a horizontal one.
+    fn get_index_label_area(&self) -> Rect {
+        let area = self.area_rectangle;
+        
+        match self.orientation {
+            BoardOrientation::Vertical =>
+                Rect::new(area.x(), area.y() - INDEX_LABEL_GAP - LABEL_LENGTH as i32, area.width(), LABEL_LENGTH),
+            BoardOrientation::Horizontal =>
+                Rect::new(area.right(), area.y(), LABEL_LENGTH, area.height()),
+        }
+    }
+    
+    fn draw_label(&self, canvas: &mut WindowCanvas, text_renderer: &TextRenderer, colour: Color) -> Result<(), String> {
+        text_renderer.draw_label(canvas, &self.count.to_string(), colour, self.get_label_area())
+    }
+    
+    fn draw_empty_slots(&self, canvas: &mut WindowCanvas, theme: &Theme) -> Result<(), String> {
+        canvas.set_draw_color(theme.empty_slot);
+        
+        for slot in self.get_empty_slots() {
+            canvas.draw_rect(self.get_slot_rect(slot))?;
+        }
         
-        let colour_white = Color::RGB(255, 255, 255);
-        let colour_not_hovered = Color::RGB(100, 100, 100);
-        let colour_hovered = Color::RGB(200, 100, 100);
+        Ok(())
+    }
 
-        let mut colour = if self.area_rectangle.contains_point(mouse_point) { 
-            colour_hovered 
+    /// Fades the colour of stones that aren't about to be removed towards the
+    /// hovered or not hovered colour; a new heap starts at the target.
+    fn step_stone_colour(&mut self, mouse_state: &MouseState, theme: &Theme, ratio: f64) {
+        let target_colour = if self.area_rectangle.contains_point(mouse_state.point) {
+            theme.stone_hovered
         } else {
-            colour_not_hovered
+            theme.stone_not_hovered
         };
+        
+        self.stone_colour = Some(match self.stone_colour {
+            Some(stone_colour) => step_colour(stone_colour, target_colour, ratio),
+            None => target_colour
+        });
+    }
+    
+    fn draw(&self, canvas: &mut WindowCanvas, mouse_state: &MouseState, theme: &Theme) -> Result<(), String> {
+        let mouse_point = mouse_state.point;
+        
+        let heap_hovered = self.area_rectangle.contains_point(mouse_point);
+        let count_to_remove = self.prepare_move(0, mouse_point)
+            .map_or(0, |nim_move| nim_move.count_to_remove);
 
         for i in 0..self.count {
             let stone_rect = self.get_nth_stone_rect(i as usize);
             
+            let colour = match self.stone_colour {
+                _ if i < count_to_remove => theme.stone_to_remove,
+                Some(stone_colour) => stone_colour,
+                None if heap_hovered => theme.stone_hovered,
+                None => theme.stone_not_hovered
+            };
+            
             canvas.set_draw_color(colour);
             canvas.fill_rect(stone_rect)?;
-            canvas.set_draw_color(colour_white);
+            canvas.set_draw_color(theme.stone_border);
             canvas.draw_rect(stone_rect)?;
+        }
 
-            if stone_rect.contains_point(mouse_point) {
-                colour = colour_not_hovered;
+        Ok(())
+    }
+    
+    /// Thick black outlines, alternating fills and a diagonal hatch on the
+    /// hovered heap instead of a subtle colour shift.
+    fn draw_high_contrast(&self, canvas: &mut WindowCanvas, mouse_state: &MouseState) -> Result<(), String> {
+        let mouse_point = mouse_state.point;
+        let outline_colour = Color::RGB(0, 0, 0);
+        
+        let heap_hovered = self.area_rectangle.contains_point(mouse_point);
+        let count_to_remove = self.prepare_move(0, mouse_point)
+            .map_or(0, |nim_move| nim_move.count_to_remove);
+
+        for i in 0..self.count {
+            let stone_rect = self.get_nth_stone_rect(i as usize);
+            
+            canvas.set_draw_color(high_contrast_stone_colour(i, i < count_to_remove));
+            canvas.fill_rect(stone_rect)?;
+            canvas.set_draw_color(outline_colour);
+            canvas.draw_rect(stone_rect)?;
+            
+            if stone_rect.width() > 4 && stone_rect.height() > 4 {
+                let inset_rect = Rect::new(
+                    stone_rect.x() + 1,
+                    stone_rect.y() + 1,
+                    stone_rect.width() - 2,
+                    stone_rect.height() - 2
+                );
+                
+                canvas.draw_rect(inset_rect)?;
+            }
+            
+            if heap_hovered {
+                canvas.draw_line(stone_rect.top_left(), stone_rect.bottom_right())?;
             }
         }
 
         Ok(())
     }
-}
-
-impl Clone for NimHeap {
-    fn clone(&self) -> NimHeap {
-        NimHeap {
-            size: self.size,
-            count: self.count,
-            corner_x: self.corner_x,
-            corner_y: self.corner_y,
-            stone_width: self.stone_width,
-            stone_height: self.stone_height,
-            area_rectangle: self.area_rectangle,
+    
+    fn draw_debug_overlay(&self, canvas: &mut WindowCanvas) -> Result<(), String> {
+        canvas.set_draw_color(Color::RGB(255, 255, 0));
+        canvas.draw_rect(self.area_rectangle)?;
+        
+        canvas.set_draw_color(Color::RGB(0, 255, 255));
+        
+        for i in 0..self.count {
+            canvas.draw_rect(self.get_nth_stone_rect(i as usize))?;
         }
+        
+        Ok(())
     }
 }
 
-pub struct NimMove {
-    pub heap_index: usize,
-    pub count_to_remove: u32,
-}
-
 pub struct NimGame {
+    core: NimCore,
     heaps: Vec<NimHeap>,
-    player: Player,
     default_heap: NimHeap,
+    random_stones_range: Option<RangeInclusive<u32>>,
+    starting_counts: Option<Vec<u32>>,
+    orientation: BoardOrientation,
+    remove_from_bottom: bool,
+    board_visible: bool,
+    show_empty_slots: bool,
+    high_contrast: bool,
+    per_heap_colours: bool,
+    removal_animation_time: Duration,
+    removal_animations: Vec<RemovalAnimation>,
+    hover_transition_time: Duration,
+    frame_time: Duration,
+    min_stone_length: u32,
+    theme: Theme,
+    recorder: Option<MoveRecorder>,
 }
 
 impl NimGame {
     pub fn new(default_heap: NimHeap) -> NimGame {
-        NimGame {
+        NimGame::with_core(default_heap, NimCore::new())
+    }
+    
+    /// A board of `heaps_count` heaps holding between `min_stones` and
+    /// `max_stones` stones each; every heap gets at least one stone.
+    pub fn new_random(heaps_count: usize, min_stones: u32, max_stones: u32) -> NimGame {
+        let max_stones = max(max_stones, 1);
+        let mut nim_game = NimGame::new(NimHeap::new(max_stones, max_stones));
+        
+        nim_game.set_random_stones_range(Some(min(min_stones, max_stones)..=max_stones));
+        
+        for _ in 0..heaps_count {
+            nim_game.add_random_heap();
+        }
+        
+        nim_game
+    }
+    
+    fn with_core(default_heap: NimHeap, core: NimCore) -> NimGame {
+        let mut nim_game = NimGame {
+            core,
             heaps: Vec::new(),
-            player: Player::One,
+            default_heap,
+            random_stones_range: None,
+            starting_counts: None,
+            orientation: BoardOrientation::default(),
+            remove_from_bottom: false,
+            board_visible: true,
+            show_empty_slots: false,
+            high_contrast: false,
+            per_heap_colours: false,
+            removal_animation_time: Duration::ZERO,
+            removal_animations: Vec::new(),
+            hover_transition_time: Duration::ZERO,
+            frame_time: Duration::ZERO,
+            min_stone_length: 1,
+            theme: Theme::default(),
+            recorder: None
+        };
+        
+        nim_game.sync_heaps();
+        
+        nim_game
+    }
+    
+    pub fn from_position(default_heap: NimHeap, counts: &[u32], max_heaps: usize) -> Result<NimGame, PositionError> {
+        let core = NimCore::from_position(default_heap.size, counts, max_heaps)?;
+        let mut nim_game = NimGame::with_core(default_heap, core);
+        
+        nim_game.starting_counts = Some(counts.to_vec());
+        
+        Ok(nim_game)
+    }
+    
+    /// A fresh game at the starting position of a recorded game, ready for
+    /// its moves to be replayed one by one with `replay_move`.
+    pub fn from_recording(default_heap: NimHeap, recorded_game: &RecordedGame) -> Result<NimGame, String> {
+        let max_count = recorded_game.counts.iter().copied().max().unwrap_or(0);
+        let default_heap = if max_count > default_heap.size {
+            NimHeap::new(max_count, max_count)
+        } else {
             default_heap
+        };
+        let max_heaps_per_move = recorded_game.moves.iter()
+            .filter_map(|recorded_turn| match recorded_turn {
+                RecordedTurn::Move(recorded_move) => Some(recorded_move.removals.len() as u32),
+                RecordedTurn::Add(_) => None
+            })
+            .max()
+            .unwrap_or(1);
+        
+        let mut nim_game = NimGame::from_position(default_heap, &recorded_game.counts, recorded_game.counts.len())
+            .map_err(|e| e.to_string())?;
+        
+        nim_game.set_variant(recorded_game.variant);
+        nim_game.set_max_heaps_per_move(max(max_heaps_per_move, 1));
+        nim_game.set_additions_per_player(recorded_game.additions_per_player);
+        
+        let players_count = recorded_game.moves.iter()
+            .map(|recorded_turn| recorded_turn.player().index())
+            .chain(std::iter::once(recorded_game.player.index()))
+            .max()
+            .unwrap_or(0) + 1;
+        
+        nim_game.core.set_players_count(players_count);
+        nim_game.core.set_player_to_move(recorded_game.player.clone());
+        
+        Ok(nim_game)
+    }
+    
+    pub fn replay_move(&mut self, recorded_turn: &RecordedTurn) -> bool {
+        if self.core.get_player_to_move() != recorded_turn.player() {
+            return false;
+        }
+        
+        match recorded_turn {
+            RecordedTurn::Move(recorded_move) => self.make_moore_move(&MooreMove {
+                removals: recorded_move.removals.clone()
+            }),
+            RecordedTurn::Add(recorded_addition) => self.add_stones(recorded_addition.heap_index, recorded_addition.count).is_ok()
         }
     }
-
-    pub fn add_default_heap(&mut self) {
-        self.heaps.push(self.default_heap.clone());
+    
+    pub fn save_to_file(&self, path: &Path) -> Result<(), String> {
+        self.core.save_to_file(path)
     }
     
-    pub fn add_random_heap(&mut self) {
-        let size = self.default_heap.size;
-        let half_size = size / 2;
+    pub fn load_from_file(path: &Path) -> Result<NimGame, String> {
+        let core = NimCore::load_from_file(path)?;
+        let max_size = core.get_sizes().iter().copied().max().unwrap_or(1);
         
-        let first_random_count = rand::random::<u32>() % half_size;
-        let second_random_count = rand::random::<u32>() % half_size;
-        let third_random_count = if size % 2 == 0 {
-            0
-        } else {
-            rand::random::<u32>() % 2
+        Ok(NimGame::with_core(NimHeap::new(max_size, max_size), core))
+    }
+    
+    /// Starts logging to `recorder`, beginning with the current position.
+    pub fn set_recorder(&mut self, recorder: Option<MoveRecorder>) {
+        self.recorder = recorder;
+        self.record_start();
+    }
+    
+    pub fn take_recorder(&mut self) -> Option<MoveRecorder> {
+        self.recorder.take()
+    }
+    
+    fn record_start(&mut self) {
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record_start(
+                self.core.get_counts(),
+                self.core.get_player_to_move(),
+                self.core.get_variant(),
+                self.core.get_additions_per_player()
+            );
+        }
+    }
+    
+    fn record_last_move(&mut self, player: &Player) {
+        let recorder = match &mut self.recorder {
+            Some(recorder) => recorder,
+            None => return
         };
         
-        let heap = NimHeap::new(
-            size,
-            first_random_count + second_random_count + third_random_count
-        );
+        if let Some(removals) = self.core.get_last_move_removals() {
+            recorder.record_move(player, removals);
+        } else if let Some((heap_index, count)) = self.core.get_last_addition() {
+            recorder.record_addition(player, heap_index, count);
+        }
+    }
+    
+    pub fn core(&self) -> &NimCore {
+        &self.core
+    }
+    
+    fn sync_heaps(&mut self) {
+        let sizes = self.core.get_sizes();
+        let counts = self.core.get_counts();
+        
+        self.heaps.truncate(counts.len());
         
-        self.heaps.push(heap);
+        for (i, (&size, &count)) in sizes.iter().zip(counts.iter()).enumerate() {
+            match self.heaps.get_mut(i) {
+                Some(heap) => {
+                    heap.size = size;
+                    heap.count = count;
+                },
+                None => self.heaps.push(NimHeap::new(size, count))
+            }
+            
+            self.heaps[i].max_removal = self.core.get_heap_removal_limit(i);
+        }
     }
-
-    pub fn remove_last_heap(&mut self) {
-        self.heaps.pop();
+    
+    pub fn set_orientation(&mut self, orientation: BoardOrientation) {
+        self.orientation = orientation;
+    }
+    
+    pub fn set_remove_from_bottom(&mut self, remove_from_bottom: bool) {
+        self.remove_from_bottom = remove_from_bottom;
+    }
+    
+    pub fn set_variant(&mut self, variant: NimVariant) {
+        self.core.set_variant(variant);
+    }
+    
+    pub fn set_ai_difficulty(&mut self, ai_difficulty: AiDifficulty, ai_optimal_move_probability: f64) {
+        self.core.set_ai_difficulty(ai_difficulty, ai_optimal_move_probability);
+    }
+    
+    pub fn set_random_stones_range(&mut self, random_stones_range: Option<RangeInclusive<u32>>) {
+        self.random_stones_range = random_stones_range;
+    }
+    
+    pub fn set_removal_animation_time(&mut self, removal_animation_time: Duration) {
+        self.removal_animation_time = removal_animation_time;
+    }
+    
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+    
+    pub fn set_additions_per_player(&mut self, additions_per_player: u32) {
+        self.core.set_additions_per_player(additions_per_player);
+    }
+    
+    pub fn get_additions_left(&self, player: &Player) -> u32 {
+        self.core.get_additions_left(player)
+    }
+    
+    pub fn add_stones(&mut self, heap_index: usize, count: u32) -> Result<(), MoveError> {
+        let player = self.core.get_player_to_move().clone();
+        
+        let result = self.core.add_stones(heap_index, count);
+        self.sync_heaps();
+        result?;
+        
+        self.record_last_move(&player);
+        
+        Ok(())
+    }
+    
+    pub fn set_high_contrast(&mut self, high_contrast: bool) {
+        self.high_contrast = high_contrast;
+    }
+    
+    pub fn set_hover_transition_time(&mut self, hover_transition_time: Duration) {
+        self.hover_transition_time = hover_transition_time;
+    }
+    
+    pub fn set_per_heap_colours(&mut self, per_heap_colours: bool) {
+        self.per_heap_colours = per_heap_colours;
+    }
+    
+    pub fn set_min_stone_length(&mut self, min_stone_length: u32) {
+        self.min_stone_length = min_stone_length.max(1);
+    }
+    
+    pub fn set_players_count(&mut self, players_count: u32) {
+        self.core.set_players_count(players_count);
+    }
+    
+    pub fn set_show_empty_slots(&mut self, show_empty_slots: bool) {
+        self.show_empty_slots = show_empty_slots;
+    }
+    
+    pub fn set_strategy(&mut self, strategy: Box<dyn NimStrategy>) {
+        self.core.set_strategy(strategy);
+    }
+    
+    pub fn set_max_removal_per_move(&mut self, max_removal_per_move: Option<u32>) {
+        self.core.set_max_removal_per_move(max_removal_per_move);
+        self.sync_heaps();
+    }
+    
+    pub fn set_heap_games(&mut self, heap_games: Vec<HeapGame>) {
+        self.core.set_heap_games(heap_games);
+        self.sync_heaps();
+    }
+    
+    pub fn set_seed(&mut self, seed: u64) {
+        self.core.set_seed(seed);
+    }
+    
+    pub fn set_history_limit(&mut self, history_limit: Option<u32>) {
+        self.core.set_history_limit(history_limit);
+    }
+    
+    pub fn set_max_heaps_per_move(&mut self, max_heaps_per_move: u32) {
+        self.core.set_max_heaps_per_move(max_heaps_per_move);
+    }
+    
+    pub fn set_heap_scoring(&mut self, heap_scoring: bool) {
+        self.core.set_heap_scoring(heap_scoring);
     }
     
-    pub fn switch_player(&mut self) {
-        self.player = self.player.next();
+    pub fn set_move_limit(&mut self, max_moves: Option<u32>, move_limit_tiebreak: MoveLimitTiebreak) {
+        self.core.set_move_limit(max_moves, move_limit_tiebreak);
     }
 
-    pub fn make_move(&mut self, nim_move: NimMove) -> bool {
-        if nim_move.heap_index >= self.heaps.len() || nim_move.count_to_remove < 1 {
-            return false;
-        }
+    pub fn total_stones(&self) -> u32 {
+        self.core.total_stones()
+    }
+    
+    pub fn binary_breakdown(&self) -> BinaryBreakdown {
+        self.core.binary_breakdown()
+    }
 
-        let heap = &mut self.heaps[nim_move.heap_index];
+    pub fn add_default_heap(&mut self) {
+        self.core.add_heap(self.default_heap.size, self.default_heap.count);
+        self.sync_heaps();
+    }
+    
+    pub fn add_random_heap(&mut self) {
+        self.core.add_random_heap(self.default_heap.size, self.random_stones_range.as_ref());
+        self.sync_heaps();
+    }
 
-        if heap.count < nim_move.count_to_remove {
-            return false;
+    pub fn remove_last_heap(&mut self) {
+        self.core.remove_last_heap();
+        self.sync_heaps();
+    }
+    
+    pub fn reset(&mut self) {
+        let heaps_count = self.core.get_heaps_count();
+        
+        self.core.clear();
+        self.removal_animations.clear();
+        
+        match &self.starting_counts {
+            Some(starting_counts) => {
+                for &count in starting_counts {
+                    self.core.add_heap(self.default_heap.size, count);
+                }
+                
+                self.sync_heaps();
+            },
+            None => {
+                for _ in 0..heaps_count {
+                    self.add_random_heap();
+                }
+            }
         }
+        
+        self.record_start();
+    }
+    
+    pub fn undo_last_move(&mut self) -> bool {
+        let undone = self.core.undo_last_move();
+        self.sync_heaps();
+        
+        if undone {
+            if let Some(recorder) = &mut self.recorder {
+                recorder.record_undo();
+            }
+        }
+        
+        undone
+    }
+    
+    pub fn redo_move(&mut self) -> bool {
+        let player = self.core.get_player_to_move().clone();
+        
+        let redone = self.core.redo_move();
+        self.sync_heaps();
+        
+        if redone {
+            self.record_last_move(&player);
+        }
+        
+        redone
+    }
 
-        heap.count -= nim_move.count_to_remove;
-        self.switch_player();
-
-        true
+    /// Lets the AI move for every seat until the game ends. Nothing is
+    /// animated and the heaps are laid out once, for the final position.
+    pub fn play_out_ai_moves(&mut self) -> u32 {
+        let mut moves_count = 0;
+        
+        while !self.core.is_game_over() {
+            let player = self.core.get_player_to_move().clone();
+            
+            if !self.core.make_ai_move() {
+                break;
+            }
+            
+            self.record_last_move(&player);
+            moves_count += 1;
+        }
+        
+        self.removal_animations.clear();
+        self.sync_heaps();
+        
+        moves_count
     }
     
-    pub fn get_player_to_move(&self) -> &Player {
-        &self.player
+    pub fn is_last_move_blunder(&mut self) -> bool {
+        self.core.is_last_move_blunder()
     }
 
-    pub fn is_game_over(&self) -> bool {
-        self.heaps.iter().all(|heap| heap.count == 0)
+    pub fn simulate(&mut self, opponent: &dyn NimStrategy, rng: &mut StdRng) -> GameResult {
+        let result = self.core.simulate(opponent, rng);
+        self.sync_heaps();
+        
+        result
+    }
+    
+    pub fn prepare_ai_move(&mut self) -> Option<NimMove> {
+        self.core.prepare_ai_move()
     }
 
-    pub fn draw_board(&mut self, canvas: &mut WindowCanvas, mouse_state: &MouseState) -> Result<(), String> {
-        let margin_top = 100;
+    fn get_removed_stone_rects(&self, nim_move: &NimMove) -> Vec<Rect> {
+        match self.heaps.get(nim_move.heap_index) {
+            Some(heap) => (0..min(nim_move.count_to_remove, heap.count))
+                .map(|i| heap.get_nth_stone_rect(i as usize))
+                .collect::<Vec<Rect>>(),
+            None => Vec::new()
+        }
+    }
+    
+    fn start_removal_animation(&mut self, stone_rects: Vec<Rect>) {
+        if !self.removal_animation_time.is_zero() {
+            self.removal_animations.push(RemovalAnimation {
+                stone_rects,
+                elapsed: Duration::ZERO
+            });
+        }
+    }
+    
+    pub fn is_legal_move(&self, nim_move: &NimMove) -> bool {
+        self.core.is_legal_move(nim_move)
+    }
+    
+    pub fn make_move(&mut self, nim_move: NimMove) -> Result<(), MoveError> {
+        self.core.validate_move(&nim_move)?;
+        
+        let stone_rects = self.get_removed_stone_rects(&nim_move);
+        let player = self.core.get_player_to_move().clone();
+        
+        let result = self.core.make_move(nim_move);
+        self.sync_heaps();
+        result?;
+        
+        self.start_removal_animation(stone_rects);
+        self.record_last_move(&player);
+        
+        Ok(())
+    }
+    
+    pub fn prepare_wythoff_ai_move(&mut self) -> Option<WythoffMove> {
+        self.core.prepare_wythoff_ai_move()
+    }
+    
+    pub fn make_wythoff_move(&mut self, wythoff_move: &WythoffMove) -> bool {
+        let stone_rects = match wythoff_move {
+            WythoffMove::Single(nim_move) => self.get_removed_stone_rects(nim_move),
+            WythoffMove::Both { count_to_remove } => (0..2)
+                .flat_map(|heap_index| self.get_removed_stone_rects(&NimMove {
+                    heap_index,
+                    count_to_remove: *count_to_remove
+                }))
+                .collect::<Vec<Rect>>()
+        };
+        
+        let player = self.core.get_player_to_move().clone();
+        
+        let made = self.core.make_wythoff_move(wythoff_move);
+        self.sync_heaps();
+        
+        if made {
+            self.start_removal_animation(stone_rects);
+            self.record_last_move(&player);
+        }
+        
+        made
+    }
+    
+    pub fn prepare_staircase_ai_move(&mut self) -> Option<StaircaseMove> {
+        self.core.prepare_staircase_ai_move()
+    }
+    
+    pub fn make_staircase_move(&mut self, staircase_move: &StaircaseMove) -> bool {
+        let stone_rects = self.get_removed_stone_rects(&NimMove {
+            heap_index: staircase_move.step_index,
+            count_to_remove: staircase_move.count_to_move
+        });
+        let player = self.core.get_player_to_move().clone();
+        
+        let made = self.core.make_staircase_move(staircase_move);
+        self.sync_heaps();
+        
+        if made {
+            self.start_removal_animation(stone_rects);
+            self.record_last_move(&player);
+        }
+        
+        made
+    }
+    
+    pub fn prepare_moore_ai_move(&mut self) -> Option<MooreMove> {
+        self.core.prepare_moore_ai_move()
+    }
+    
+    pub fn make_moore_move(&mut self, moore_move: &MooreMove) -> bool {
+        let stone_rects = moore_move.removals.iter()
+            .flat_map(|nim_move| self.get_removed_stone_rects(nim_move))
+            .collect::<Vec<Rect>>();
+        let player = self.core.get_player_to_move().clone();
+        
+        let made = self.core.make_moore_move(moore_move);
+        self.sync_heaps();
+        
+        if made {
+            self.start_removal_animation(stone_rects);
+            self.record_last_move(&player);
+        }
+        
+        made
+    }
+    
+    /// False while the window is below the minimum board size.
+    pub fn is_board_visible(&self) -> bool {
+        self.board_visible
+    }
+    
+    pub fn is_animating(&self) -> bool {
+        !self.removal_animations.is_empty()
+    }
+    
+    pub fn advance_animations(&mut self, frame_time: Duration) {
+        self.frame_time = frame_time;
+        
+        let removal_animation_time = self.removal_animation_time;
+        
+        for animation in self.removal_animations.iter_mut() {
+            animation.elapsed += frame_time;
+        }
+        
+        self.removal_animations.retain(|animation| animation.elapsed < removal_animation_time);
+    }
+    
+    pub fn apply_moves(&mut self, moves: &[NimMove]) -> Result<(), (usize, MoveError)> {
+        for (i, nim_move) in moves.iter().enumerate() {
+            let player = self.core.get_player_to_move().clone();
+            
+            let result = self.core.apply_moves(&[*nim_move]);
+            self.sync_heaps();
+            
+            result.map_err(|(_, error)| (i, error))?;
+            self.record_last_move(&player);
+        }
+        
+        Ok(())
+    }
+    
+    pub fn layout(&mut self, window_size: (u32, u32)) -> Option<Rect> {
+        let margin_top = MARGIN_TOP;
 
-        let window_size = canvas.output_size()?;
+        self.board_visible = window_size.0 >= MIN_BOARD_WIDTH && window_size.1 >= MIN_BOARD_HEIGHT;
+        
+        if !self.board_visible || self.heaps.is_empty() {
+            return None;
+        }
+        
         let game_area_width = window_size.0 as f64 * 0.9;
-        let game_area_height = (window_size.1 - margin_top) as f64 * 0.9;
+        let game_area_height = window_size.1.saturating_sub(margin_top) as f64 * 0.9;
 
         let margin_x = (window_size.0 as f64 - game_area_width) / 2.0;
         let margin_between_heaps = 10.0;
@@ -212,42 +930,183 @@ impl NimGame {
 
         let count_of_stones = self.heaps.iter()
             .map(|heap| heap.size as usize).max().unwrap_or(1);
-
-        let heap_width_with_margin = (game_area_width - half_margin_between_heaps) / self.heaps.len() as f64 - half_margin_between_heaps;
-        let heap_height = game_area_height;
-
-        let stone_height = heap_height / count_of_stones as f64;
+        let heaps_len = self.heaps.len() as f64;
         
         let game_area_rect = Rect::new(
             margin_x as i32,
             margin_top as i32,
-            game_area_width as u32,
-            game_area_height as u32
+            clamp_length(game_area_width),
+            clamp_length(game_area_height)
         );
 
+        let orientation = self.orientation;
+        let remove_from_bottom = self.remove_from_bottom;
+        let min_stone_length = self.min_stone_length as f64;
+
         for (i, heap) in self.heaps.iter_mut().enumerate() {
-            let x = i as f64 * (heap_width_with_margin + half_margin_between_heaps)
-                + margin_x + half_margin_between_heaps;
-            let y = margin_top as f64 + game_area_height - heap_height;
+            let (rectangle, stone_length) = match orientation {
+                BoardOrientation::Vertical => {
+                    let heap_width_with_margin = (game_area_width - half_margin_between_heaps) / heaps_len - half_margin_between_heaps;
+                    let heap_height = game_area_height;
+                    let stone_height = heap_height / count_of_stones as f64;
+                    
+                    let x = i as f64 * (heap_width_with_margin + half_margin_between_heaps)
+                        + margin_x + half_margin_between_heaps;
+                    let y = margin_top as f64 + game_area_height - heap_height;
+                    
+                    let rectangle =
+                        Rect::new(x as i32, y as i32, clamp_length(heap_width_with_margin), clamp_length(heap_height));
+                    
+                    (rectangle, stone_height)
+                },
+                BoardOrientation::Horizontal => {
+                    let heap_height_with_margin = (game_area_height - half_margin_between_heaps) / heaps_len - half_margin_between_heaps;
+                    let heap_width = game_area_width;
+                    let stone_width = heap_width / count_of_stones as f64;
+                    
+                    let x = margin_x;
+                    let y = i as f64 * (heap_height_with_margin + half_margin_between_heaps)
+                        + margin_top as f64 + half_margin_between_heaps;
+                    
+                    let rectangle =
+                        Rect::new(x as i32, y as i32, clamp_length(heap_width), clamp_length(heap_height_with_margin));
+                    
+                    (rectangle, stone_width)
+                }
+            };
 
-            let colour = Color::RGB(0, 0, 0);
-            let rectangle =
-                Rect::new(x as i32, y as i32, heap_width_with_margin as u32, heap_height as u32);
+            heap.set_heap_sizes(rectangle, stone_length.max(min_stone_length), orientation);
+            heap.remove_from_bottom = remove_from_bottom;
+        }
 
-            canvas.set_draw_color(colour);
-            canvas.draw_rect(rectangle)?;
+        Some(game_area_rect)
+    }
 
-            heap.set_heap_sizes(rectangle, stone_height);
-            heap.draw(canvas, mouse_state)?;
+    pub fn draw_board(&mut self, canvas: &mut WindowCanvas, mouse_state: &MouseState) -> Result<(), GameError> {
+        let window_size = canvas.output_size().map_err(GameError::Render)?;
+        let game_area_rect = match self.layout(window_size) {
+            Some(game_area_rect) => game_area_rect,
+            None => return Ok(())
+        };
+
+        let heaps_count = self.heaps.len();
+        let hover_ratio = transition_ratio(self.frame_time, self.hover_transition_time);
+        
+        for (heap_index, heap) in self.heaps.iter_mut().enumerate() {
+            let theme = if self.per_heap_colours {
+                self.theme.with_heap_hue(heap_index, heaps_count)
+            } else {
+                self.theme
+            };
+            
+            heap.step_stone_colour(mouse_state, &theme, hover_ratio);
         }
+        
+        for (heap_index, heap) in self.heaps.iter().enumerate() {
+            canvas.set_draw_color(self.theme.heap_border);
+            canvas.draw_rect(heap.area_rectangle).map_err(GameError::Render)?;
+            
+            if self.show_empty_slots {
+                heap.draw_empty_slots(canvas, &self.theme).map_err(GameError::Render)?;
+            }
+            
+            if self.high_contrast {
+                heap.draw_high_contrast(canvas, mouse_state).map_err(GameError::Render)?;
+            } else {
+                let theme = if self.per_heap_colours {
+                    self.theme.with_heap_hue(heap_index, heaps_count)
+                } else {
+                    self.theme
+                };
+                
+                heap.draw(canvas, mouse_state, &theme).map_err(GameError::Render)?;
+            }
+        }
+
+        self.draw_removal_animations(canvas).map_err(GameError::Render)?;
 
-        canvas.set_draw_color(Color::RGB(255, 255, 255));
-        canvas.draw_rect(game_area_rect)?;
+        canvas.set_draw_color(self.theme.board_border);
+        canvas.draw_rect(game_area_rect).map_err(GameError::Render)?;
 
         Ok(())
     }
     
+    fn draw_removal_animations(&self, canvas: &mut WindowCanvas) -> Result<(), String> {
+        canvas.set_blend_mode(BlendMode::Blend);
+        
+        for animation in self.removal_animations.iter() {
+            let progress = animation_progress(animation.elapsed, self.removal_animation_time);
+            let alpha = ((1.0 - progress) * 255.0) as u8;
+            let scale = 1.0 - progress;
+            let colour = self.theme.stone_to_remove;
+            
+            canvas.set_draw_color(Color::RGBA(colour.r, colour.g, colour.b, alpha));
+            
+            for stone_rect in animation.stone_rects.iter() {
+                let width = clamp_length(stone_rect.width() as f64 * scale);
+                let height = clamp_length(stone_rect.height() as f64 * scale);
+                
+                canvas.fill_rect(Rect::from_center(stone_rect.center(), width, height))?;
+            }
+        }
+        
+        canvas.set_blend_mode(BlendMode::None);
+        
+        Ok(())
+    }
+    
+    pub fn get_heap_index_at(&self, point: Point) -> Option<usize> {
+        if !self.board_visible {
+            return None;
+        }
+        
+        self.heaps.iter().position(|heap| heap.area_rectangle.contains_point(point))
+    }
+    
+    pub fn draw_count_labels(&self, canvas: &mut WindowCanvas, text_renderer: &TextRenderer, colour: Color) -> Result<(), String> {
+        if !self.board_visible {
+            return Ok(());
+        }
+        
+        for heap in self.heaps.iter() {
+            heap.draw_label(canvas, text_renderer, colour)?;
+        }
+        
+        Ok(())
+    }
+    
+    pub fn draw_index_labels(&self, canvas: &mut WindowCanvas, text_renderer: &TextRenderer, colour: Color) -> Result<(), String> {
+        if !self.board_visible {
+            return Ok(());
+        }
+        
+        for (i, heap) in self.heaps.iter().enumerate() {
+            text_renderer.draw_label(canvas, &(i + 1).to_string(), colour, heap.get_index_label_area())?;
+        }
+        
+        Ok(())
+    }
+    
+    pub fn draw_debug_overlay(&self, canvas: &mut WindowCanvas, mouse_state: &MouseState) -> Result<(), String> {
+        for heap in self.heaps.iter() {
+            heap.draw_debug_overlay(canvas)?;
+        }
+        
+        let point = mouse_state.point;
+        let window_size = canvas.output_size()?;
+        
+        canvas.set_draw_color(Color::RGB(255, 0, 255));
+        canvas.draw_line(Point::new(point.x(), 0), Point::new(point.x(), window_size.1 as i32))?;
+        canvas.draw_line(Point::new(0, point.y()), Point::new(window_size.0 as i32, point.y()))?;
+        
+        Ok(())
+    }
+    
     pub fn prepare_player_move(&self, point: Point) -> Option<NimMove> {
+        if !self.board_visible {
+            return None;
+        }
+        
         for (i, heap) in self.heaps.iter().enumerate() {
             if let Some(nim_move) = heap.prepare_move(i, point) {
                 return Some(nim_move);
@@ -257,56 +1116,80 @@ impl NimGame {
         None
     }
     
-    pub fn prepare_random_move(&self) -> Option<NimMove> {
-        let all_non_zero_indices = self.heaps.iter().enumerate().filter_map(|(index, heap)| {
-            if heap.get_count() > 0 {
-                Some(index)
-            } else {
-                None
+    /// The removals selected by dragging along `path`, which starts where the
+    /// button was pressed. Entering another heap starts a new removal there
+    /// until `max_heaps` heaps have been dragged across; every other point
+    /// extends the current one. Dragging back over a heap replaces its removal.
+    pub fn prepare_drag_move(&self, path: &[Point], max_heaps: u32) -> Vec<NimMove> {
+        let mut segments: Vec<(usize, Point, Point)> = Vec::new();
+        
+        for &point in path {
+            let heap_index = self.get_heap_index_at(point);
+            let current_heap = segments.last().map(|segment| segment.0);
+            let starts_segment = match (current_heap, heap_index) {
+                (None, None) => return Vec::new(),
+                (None, Some(_)) => true,
+                (Some(_), None) => false,
+                (Some(current_heap), Some(heap_index)) => current_heap != heap_index
+                    && (segments.len() < max_heaps as usize || segments.iter().any(|segment| segment.0 == heap_index))
+            };
+            
+            match (starts_segment, heap_index, segments.last_mut()) {
+                (true, Some(heap_index), _) => {
+                    segments.retain(|segment| segment.0 != heap_index);
+                    segments.push((heap_index, point, point));
+                },
+                (_, _, Some(segment)) => segment.2 = point,
+                _ => {}
             }
-        }).collect::<Vec<usize>>();
+        }
         
-        if all_non_zero_indices.is_empty() {
+        segments.iter()
+            .filter_map(|&(heap_index, start, end)| self.heaps[heap_index].prepare_drag_move(heap_index, start, end))
+            .collect()
+    }
+    
+    pub fn prepare_single_stone_move(&self, point: Point) -> Option<NimMove> {
+        if !self.board_visible {
             return None;
         }
         
-        let random_vector_index = rand::random::<usize>() % all_non_zero_indices.len();
-        let heap_index = all_non_zero_indices[random_vector_index];
+        self.heaps.iter().enumerate()
+            .find_map(|(i, heap)| heap.prepare_single_stone_move(i, point))
+    }
+    
+    pub fn draw_heap_highlight(&self, canvas: &mut WindowCanvas, heap_index: usize) -> Result<(), String> {
+        self.draw_heap_outline(canvas, heap_index, Color::RGB(255, 220, 0))
+    }
+    
+    pub fn draw_heap_outline(&self, canvas: &mut WindowCanvas, heap_index: usize, colour: Color) -> Result<(), String> {
+        if !self.board_visible {
+            return Ok(());
+        }
         
-        let heap_count = self.heaps[heap_index].get_count();
-        let count_to_remove = rand::random::<u32>() % heap_count + 1;
+        if let Some(heap) = self.heaps.get(heap_index) {
+            canvas.set_draw_color(colour);
+            canvas.draw_rect(heap.area_rectangle)?;
+        }
         
-        Some(NimMove {
-            heap_index,
-            count_to_remove
-        })
+        Ok(())
     }
     
-    pub fn prepare_ai_move(&self) -> Option<NimMove> {
-        let all_counts_xor =
-            self.heaps.iter().fold(0, |acc, heap| acc ^ heap.get_count());
-        let get_all_suitable_indices =
-            self.heaps.iter().enumerate().filter_map(|(index, heap)| {
-                let count = heap.get_count();
-                if count > (count ^ all_counts_xor) {
-                    Some(index)
-                } else {
-                    None
-                }
-            }).collect::<Vec<usize>>();
-        if get_all_suitable_indices.is_empty() {
-            return self.prepare_random_move();
+    pub fn draw_move_preview(&self, canvas: &mut WindowCanvas, nim_move: &NimMove) -> Result<(), String> {
+        if let Some(heap) = self.heaps.get(nim_move.heap_index) {
+            canvas.set_draw_color(Color::RGB(255, 0, 0));
+            
+            for i in 0..min(nim_move.count_to_remove, heap.count) {
+                canvas.draw_rect(heap.get_nth_stone_rect(i as usize))?;
+            }
         }
+        
+        Ok(())
+    }
+}
 
-        let random_vector_index = rand::random::<usize>() % get_all_suitable_indices.len();
-        let heap_index = get_all_suitable_indices[random_vector_index];
-        let heap_count = self.heaps[heap_index].get_count();
-
-        let count_to_remove = heap_count - (heap_count ^ all_counts_xor);
-
-        Some(NimMove {
-            heap_index,
-            count_to_remove
-        })
+impl Display for NimGame {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.core)
     }
-}
\ No newline at end of file
+}
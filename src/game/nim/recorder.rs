@@ -0,0 +1,164 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::game::system::Player;
+use super::nim_core::{NimMove, NimVariant};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedMove {
+    pub player: Player,
+    pub removals: Vec<NimMove>,
+}
+
+/// Poker Nim: `count` stones put back on a heap.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedAddition {
+    pub player: Player,
+    pub heap_index: usize,
+    pub count: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecordedTurn {
+    Move(RecordedMove),
+    Add(RecordedAddition)
+}
+
+impl RecordedTurn {
+    pub fn player(&self) -> &Player {
+        match self {
+            RecordedTurn::Move(recorded_move) => &recorded_move.player,
+            RecordedTurn::Add(recorded_addition) => &recorded_addition.player
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum RecordedEvent {
+    Start {
+        counts: Vec<u32>,
+        player: Player,
+        #[serde(default)]
+        variant: NimVariant,
+        #[serde(default)]
+        additions_per_player: u32
+    },
+    Move(RecordedMove),
+    Add(RecordedAddition),
+    Undo
+}
+
+/// One game from a move log: the starting heap counts and the turns that
+/// were played from there, with undone turns already dropped. Logs from
+/// before the variant was recorded replay with the normal rules.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedGame {
+    pub counts: Vec<u32>,
+    pub player: Player,
+    pub variant: NimVariant,
+    pub additions_per_player: u32,
+    pub moves: Vec<RecordedTurn>,
+}
+
+/// Appends every game and move to a log, one JSON record per line.
+pub struct MoveRecorder {
+    writer: Box<dyn Write>,
+}
+
+impl MoveRecorder {
+    pub fn new(writer: Box<dyn Write>) -> MoveRecorder {
+        MoveRecorder {
+            writer
+        }
+    }
+
+    pub fn create(path: &Path) -> io::Result<MoveRecorder> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+
+        Ok(MoveRecorder::new(Box::new(file)))
+    }
+
+    fn write_event(&mut self, event: &RecordedEvent) {
+        let result = serde_json::to_string(event)
+            .map_err(|e| e.to_string())
+            .and_then(|line| writeln!(self.writer, "{}", line).map_err(|e| e.to_string()));
+
+        if let Err(e) = result {
+            println!("Failed to record move: {}", e);
+        }
+    }
+
+    pub fn record_start(&mut self, counts: &[u32], player: &Player, variant: NimVariant, additions_per_player: u32) {
+        self.write_event(&RecordedEvent::Start {
+            counts: counts.to_vec(),
+            player: player.clone(),
+            variant,
+            additions_per_player
+        });
+    }
+
+    pub fn record_move(&mut self, player: &Player, removals: &[NimMove]) {
+        self.write_event(&RecordedEvent::Move(RecordedMove {
+            player: player.clone(),
+            removals: removals.to_vec()
+        }));
+    }
+
+    pub fn record_addition(&mut self, player: &Player, heap_index: usize, count: u32) {
+        self.write_event(&RecordedEvent::Add(RecordedAddition {
+            player: player.clone(),
+            heap_index,
+            count
+        }));
+    }
+
+    pub fn record_undo(&mut self) {
+        self.write_event(&RecordedEvent::Undo);
+    }
+
+    pub fn load(path: &Path) -> Result<Vec<RecordedGame>, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        let mut games: Vec<RecordedGame> = Vec::new();
+
+        for (line_index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let event: RecordedEvent = serde_json::from_str(line)
+                .map_err(|e| format!("Invalid record on line {} of {}: {}", line_index + 1, path.display(), e))?;
+
+            match event {
+                RecordedEvent::Start { counts, player, variant, additions_per_player } => games.push(RecordedGame {
+                    counts,
+                    player,
+                    variant,
+                    additions_per_player,
+                    moves: Vec::new()
+                }),
+                RecordedEvent::Move(recorded_move) => match games.last_mut() {
+                    Some(game) => game.moves.push(RecordedTurn::Move(recorded_move)),
+                    None => return Err(format!("Move before game start on line {} of {}", line_index + 1, path.display()))
+                },
+                RecordedEvent::Add(recorded_addition) => match games.last_mut() {
+                    Some(game) => game.moves.push(RecordedTurn::Add(recorded_addition)),
+                    None => return Err(format!("Addition before game start on line {} of {}", line_index + 1, path.display()))
+                },
+                RecordedEvent::Undo => {
+                    if let Some(game) = games.last_mut() {
+                        game.moves.pop();
+                    }
+                }
+            }
+        }
+
+        Ok(games)
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::Path;
+use super::nim_game::NimGame;
+use super::recorder::{MoveRecorder, RecordedGame};
+
+/// Steps through the games of a move log. Position `move_index` of a game is
+/// the board after its first `move_index` moves; stepping back undoes the
+/// last replayed move.
+pub struct ReplayViewer {
+    games: Vec<RecordedGame>,
+    game_index: usize,
+    move_index: usize,
+}
+
+impl ReplayViewer {
+    pub fn load(path: &Path) -> Result<ReplayViewer, String> {
+        let games = MoveRecorder::load(path)?;
+
+        if games.is_empty() {
+            return Err(format!("No games recorded in {}", path.display()));
+        }
+
+        Ok(ReplayViewer {
+            games,
+            game_index: 0,
+            move_index: 0
+        })
+    }
+
+    pub fn current_game(&self) -> &RecordedGame {
+        &self.games[self.game_index]
+    }
+
+    pub fn get_game_index(&self) -> usize {
+        self.game_index
+    }
+
+    pub fn get_games_count(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn get_move_index(&self) -> usize {
+        self.move_index
+    }
+
+    pub fn get_moves_count(&self) -> usize {
+        self.current_game().moves.len()
+    }
+
+    /// Switches to another game at its starting position; the caller builds
+    /// the board for it from `current_game`.
+    pub fn select_game(&mut self, game_index: usize) -> bool {
+        if game_index >= self.games.len() {
+            return false;
+        }
+
+        self.game_index = game_index;
+        self.move_index = 0;
+
+        true
+    }
+
+    pub fn step_forward(&mut self, nim_game: &mut NimGame) -> bool {
+        let recorded_move = match self.current_game().moves.get(self.move_index) {
+            Some(recorded_move) => recorded_move.clone(),
+            None => return false
+        };
+
+        if !nim_game.replay_move(&recorded_move) {
+            println!("Illegal move {} in game {}", self.move_index + 1, self.game_index + 1);
+            return false;
+        }
+
+        self.move_index += 1;
+
+        true
+    }
+
+    pub fn step_backward(&mut self, nim_game: &mut NimGame) -> bool {
+        if self.move_index == 0 || !nim_game.undo_last_move() {
+            return false;
+        }
+
+        self.move_index -= 1;
+
+        true
+    }
+}
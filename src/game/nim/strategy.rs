@@ -0,0 +1,516 @@
+use std::cmp::min;
+use rand::rngs::StdRng;
+use rand::RngCore;
+use super::nim_core::{HeapGame, MooreMove, NimMove, NimVariant, StaircaseMove, WinningMoveTiebreak, WythoffMove};
+
+pub trait NimStrategy {
+    /// A move that wins with perfect play afterwards, or `None` if the
+    /// strategy knows of no such move. Shared by the AI and the hints.
+    fn find_winning_move(&self, _heaps: &[u32]) -> Option<NimMove> {
+        None
+    }
+
+    fn choose_move(&self, heaps: &[u32], rng: &mut StdRng) -> Option<NimMove>;
+}
+
+pub struct RandomStrategy {
+    max_removal: Option<u32>,
+}
+
+impl RandomStrategy {
+    pub fn new(max_removal: Option<u32>) -> RandomStrategy {
+        RandomStrategy {
+            max_removal
+        }
+    }
+}
+
+impl NimStrategy for RandomStrategy {
+    fn choose_move(&self, heaps: &[u32], rng: &mut StdRng) -> Option<NimMove> {
+        let all_non_zero_indices = heaps.iter().enumerate().filter_map(|(index, &count)| {
+            if count > 0 {
+                Some(index)
+            } else {
+                None
+            }
+        }).collect::<Vec<usize>>();
+
+        if all_non_zero_indices.is_empty() {
+            return None;
+        }
+
+        let random_vector_index = rng.next_u32() as usize % all_non_zero_indices.len();
+        let heap_index = all_non_zero_indices[random_vector_index];
+
+        let heap_count = heaps[heap_index];
+        let max_count = self.max_removal.map_or(heap_count, |max_removal| min(heap_count, max_removal));
+        let count_to_remove = rng.next_u32() % max_count + 1;
+
+        Some(NimMove {
+            heap_index,
+            count_to_remove
+        })
+    }
+}
+
+/// Always empties the largest heap, picking the first one on ties.
+/// With a removal cap it takes as many stones from that heap as allowed.
+pub struct GreedyStrategy {
+    max_removal: Option<u32>,
+}
+
+impl GreedyStrategy {
+    pub fn new(max_removal: Option<u32>) -> GreedyStrategy {
+        GreedyStrategy {
+            max_removal
+        }
+    }
+}
+
+impl NimStrategy for GreedyStrategy {
+    fn choose_move(&self, heaps: &[u32], _rng: &mut StdRng) -> Option<NimMove> {
+        let (heap_index, &count) = heaps.iter().enumerate()
+            .filter(|(_, &count)| count > 0)
+            .rev()
+            .max_by_key(|(_, &count)| count)?;
+
+        Some(NimMove {
+            heap_index,
+            count_to_remove: self.max_removal.map_or(count, |max_removal| min(count, max_removal))
+        })
+    }
+}
+
+/// Moves to a position with zero nim-sum (with the misère endgame correction),
+/// falling back to a random move when the position is already lost. When
+/// several heaps allow a winning move, `tiebreak` picks one of them.
+pub struct OptimalStrategy {
+    variant: NimVariant,
+    tiebreak: WinningMoveTiebreak,
+}
+
+impl OptimalStrategy {
+    pub fn new(variant: NimVariant, tiebreak: WinningMoveTiebreak) -> OptimalStrategy {
+        OptimalStrategy {
+            variant,
+            tiebreak
+        }
+    }
+
+    fn find_winning_moves(heaps: &[u32]) -> Vec<NimMove> {
+        let all_counts_xor = heaps.iter().fold(0, |acc, count| acc ^ count);
+
+        heaps.iter().enumerate()
+            .filter(|(_, &count)| count > (count ^ all_counts_xor))
+            .map(|(heap_index, &count)| NimMove {
+                heap_index,
+                count_to_remove: count - (count ^ all_counts_xor)
+            })
+            .collect()
+    }
+
+    fn find_misere_endgame_move(&self, heaps: &[u32]) -> Option<Option<NimMove>> {
+        let large_heap_indices = heaps.iter().enumerate()
+            .filter(|(_, &count)| count > 1)
+            .map(|(index, _)| index)
+            .collect::<Vec<usize>>();
+        let single_stone_heaps_count = heaps.iter()
+            .filter(|&&count| count == 1)
+            .count();
+
+        match large_heap_indices.as_slice() {
+            [] => {
+                if single_stone_heaps_count % 2 == 1 {
+                    return Some(None);
+                }
+
+                let heap_index = heaps.iter().position(|&count| count == 1);
+
+                Some(heap_index.map(|heap_index| NimMove {
+                    heap_index,
+                    count_to_remove: 1
+                }))
+            },
+            [heap_index] => {
+                let heap_count = heaps[*heap_index];
+                let count_to_leave = if single_stone_heaps_count % 2 == 0 { 1 } else { 0 };
+
+                Some(Some(NimMove {
+                    heap_index: *heap_index,
+                    count_to_remove: heap_count - count_to_leave
+                }))
+            },
+            _ => None
+        }
+    }
+}
+
+impl NimStrategy for OptimalStrategy {
+    fn find_winning_move(&self, heaps: &[u32]) -> Option<NimMove> {
+        if self.variant == NimVariant::Misere {
+            if let Some(endgame_move) = self.find_misere_endgame_move(heaps) {
+                return endgame_move;
+            }
+        }
+
+        let winning_moves = Self::find_winning_moves(heaps);
+
+        match self.tiebreak {
+            WinningMoveTiebreak::MostStonesRemoved => winning_moves.into_iter()
+                .rev()
+                .max_by_key(|nim_move| nim_move.count_to_remove),
+            WinningMoveTiebreak::Random => {
+                let position_hash = heaps.iter()
+                    .fold(0xcbf2_9ce4_8422_2325_u64, |hash, &count| (hash ^ count as u64).wrapping_mul(0x100_0000_01b3));
+
+                winning_moves.get(position_hash as usize % winning_moves.len().max(1)).copied()
+            },
+            WinningMoveTiebreak::LowestIndex => winning_moves.into_iter().next()
+        }
+    }
+
+    fn choose_move(&self, heaps: &[u32], rng: &mut StdRng) -> Option<NimMove> {
+        self.find_winning_move(heaps)
+            .or_else(|| RandomStrategy::new(None).choose_move(heaps, rng))
+    }
+}
+
+/// Optimal play when at most `max_removal` stones can be taken per move:
+/// a heap of `n` stones is worth `n % (max_removal + 1)`, so the strategy
+/// moves to a position where the nim-sum of those values is zero.
+pub struct SubtractionStrategy {
+    max_removal: u32,
+}
+
+impl SubtractionStrategy {
+    pub fn new(max_removal: u32) -> SubtractionStrategy {
+        SubtractionStrategy {
+            max_removal
+        }
+    }
+}
+
+impl NimStrategy for SubtractionStrategy {
+    fn find_winning_move(&self, heaps: &[u32]) -> Option<NimMove> {
+        let period = self.max_removal + 1;
+        let values_xor = heaps.iter().fold(0, |acc, count| acc ^ (count % period));
+        let heap_index = heaps.iter().position(|&count| {
+            let value = count % period;
+
+            value > (value ^ values_xor)
+        })?;
+        let value = heaps[heap_index] % period;
+
+        Some(NimMove {
+            heap_index,
+            count_to_remove: value - (value ^ values_xor)
+        })
+    }
+
+    fn choose_move(&self, heaps: &[u32], rng: &mut StdRng) -> Option<NimMove> {
+        self.find_winning_move(heaps)
+            .or_else(|| RandomStrategy::new(Some(self.max_removal)).choose_move(heaps, rng))
+    }
+}
+
+/// Optimal play on a sum of different games, one per heap: the sum is lost
+/// exactly when the Grundy values of its heaps XOR to zero, so the strategy
+/// takes one heap down to the value that makes the XOR zero again.
+pub struct GameSumStrategy {
+    heap_games: Vec<HeapGame>,
+}
+
+impl GameSumStrategy {
+    pub fn new(heap_games: Vec<HeapGame>) -> GameSumStrategy {
+        GameSumStrategy {
+            heap_games
+        }
+    }
+
+    fn heap_game(&self, heap_index: usize) -> HeapGame {
+        self.heap_games.get(heap_index).copied().unwrap_or_default()
+    }
+
+    /// A random legal move, respecting the removal limit of each heap's game.
+    pub fn choose_random_move(&self, heaps: &[u32], rng: &mut StdRng) -> Option<NimMove> {
+        let nim_move = RandomStrategy::new(None).choose_move(heaps, rng)?;
+        let heap_count = heaps[nim_move.heap_index];
+        let max_count = self.heap_game(nim_move.heap_index).max_removal()
+            .map_or(heap_count, |max_removal| min(heap_count, max_removal));
+
+        Some(NimMove {
+            heap_index: nim_move.heap_index,
+            count_to_remove: rng.next_u32() % max_count + 1
+        })
+    }
+}
+
+impl NimStrategy for GameSumStrategy {
+    fn find_winning_move(&self, heaps: &[u32]) -> Option<NimMove> {
+        let values_xor = heaps.iter().enumerate()
+            .fold(0, |acc, (heap_index, &count)| acc ^ self.heap_game(heap_index).grundy_value(count));
+
+        heaps.iter().enumerate().find_map(|(heap_index, &count)| {
+            let value = self.heap_game(heap_index).grundy_value(count);
+
+            (value > (value ^ values_xor)).then(|| NimMove {
+                heap_index,
+                count_to_remove: value - (value ^ values_xor)
+            })
+        })
+    }
+
+    fn choose_move(&self, heaps: &[u32], rng: &mut StdRng) -> Option<NimMove> {
+        self.find_winning_move(heaps)
+            .or_else(|| self.choose_random_move(heaps, rng))
+    }
+}
+
+/// Winning play for Moore's Nim, where a move may take stones from up to
+/// `max_heaps` heaps: in a losing position every binary column of the heap
+/// counts sums to a multiple of `max_heaps + 1`.
+pub struct MooreStrategy {
+    max_heaps: u32,
+}
+
+impl MooreStrategy {
+    pub fn new(max_heaps: u32) -> MooreStrategy {
+        MooreStrategy {
+            max_heaps
+        }
+    }
+
+    pub fn find_winning_move(&self, heaps: &[u32]) -> Option<MooreMove> {
+        let modulus = self.max_heaps + 1;
+        let bits_count = 32 - heaps.iter().copied().max()?.leading_zeros();
+
+        let mut new_counts = heaps.to_vec();
+        let mut chosen = vec![false; heaps.len()];
+
+        for bit in (0..bits_count).rev() {
+            let mask = 1 << bit;
+            let chosen_count = chosen.iter().filter(|&&is_chosen| is_chosen).count() as u32;
+            let unchosen_ones = (0..heaps.len())
+                .filter(|&i| !chosen[i] && heaps[i] & mask != 0)
+                .collect::<Vec<usize>>();
+            let column_remainder = unchosen_ones.len() as u32 % modulus;
+
+            if column_remainder == 0 {
+                continue;
+            }
+
+            if chosen_count + column_remainder <= self.max_heaps {
+                for &i in unchosen_ones.iter().take(column_remainder as usize) {
+                    chosen[i] = true;
+                    new_counts[i] = ((heaps[i] >> bit) & !1) << bit;
+                }
+            } else {
+                let ones_needed = (modulus - column_remainder) as usize;
+
+                for i in (0..heaps.len()).filter(|&i| chosen[i]).take(ones_needed) {
+                    new_counts[i] |= mask;
+                }
+            }
+        }
+
+        let removals = (0..heaps.len())
+            .filter(|&i| chosen[i])
+            .map(|i| NimMove {
+                heap_index: i,
+                count_to_remove: heaps[i] - new_counts[i]
+            })
+            .collect::<Vec<NimMove>>();
+
+        if removals.is_empty() {
+            return None;
+        }
+
+        Some(MooreMove {
+            removals
+        })
+    }
+
+    pub fn choose_random_move(&self, heaps: &[u32], rng: &mut StdRng) -> Option<MooreMove> {
+        RandomStrategy::new(None).choose_move(heaps, rng)
+            .map(|nim_move| MooreMove {
+                removals: vec![nim_move]
+            })
+    }
+
+    pub fn choose_move(&self, heaps: &[u32], rng: &mut StdRng) -> Option<MooreMove> {
+        self.find_winning_move(heaps)
+            .or_else(|| self.choose_random_move(heaps, rng))
+    }
+}
+
+/// Winning play for Wythoff's game on two heaps. The losing positions are
+/// the pairs `(floor(n * phi), floor(n * phi^2))`, where the second value is
+/// also the first plus `n`.
+pub struct WythoffStrategy;
+
+impl WythoffStrategy {
+    fn cold_position(n: u32) -> (u32, u32) {
+        let golden_ratio = (1.0 + 5.0_f64.sqrt()) / 2.0;
+        let smaller = (n as f64 * golden_ratio).floor() as u32;
+
+        (smaller, smaller + n)
+    }
+
+    pub fn find_winning_move(&self, heaps: &[u32]) -> Option<WythoffMove> {
+        let (smaller_index, larger_index) = match heaps {
+            [first, second] if first <= second => (0, 1),
+            [_, _] => (1, 0),
+            _ => return None
+        };
+        let smaller = heaps[smaller_index];
+        let larger = heaps[larger_index];
+
+        let difference = larger - smaller;
+        let (cold_smaller, _) = Self::cold_position(difference);
+
+        if smaller == cold_smaller {
+            return None;
+        }
+
+        if smaller > cold_smaller {
+            return Some(WythoffMove::Both {
+                count_to_remove: smaller - cold_smaller
+            });
+        }
+
+        let (heap_index, target_count) = (0..=smaller)
+            .map(Self::cold_position)
+            .find_map(|(cold_smaller, cold_larger)| {
+                if cold_smaller == smaller && larger > cold_larger {
+                    Some((larger_index, cold_larger))
+                } else if cold_larger == smaller {
+                    Some((larger_index, cold_smaller))
+                } else {
+                    None
+                }
+            })?;
+
+        Some(WythoffMove::Single(NimMove {
+            heap_index,
+            count_to_remove: heaps[heap_index] - target_count
+        }))
+    }
+}
+
+/// Winning play for Fibonacci Nim: take the smallest term of the Zeckendorf
+/// representation of the heap whenever the current limit allows it.
+pub struct FibonacciStrategy {
+    max_removal: u32,
+}
+
+impl FibonacciStrategy {
+    pub fn new(max_removal: u32) -> FibonacciStrategy {
+        FibonacciStrategy {
+            max_removal
+        }
+    }
+
+    fn smallest_zeckendorf_term(count: u32) -> u32 {
+        let mut fibonacci_numbers: Vec<u32> = vec![1, 2];
+
+        while fibonacci_numbers[fibonacci_numbers.len() - 1] <= count {
+            let length = fibonacci_numbers.len();
+
+            fibonacci_numbers.push(fibonacci_numbers[length - 2] + fibonacci_numbers[length - 1]);
+        }
+
+        let mut remaining = count;
+        let mut smallest_term = count;
+
+        for &fibonacci_number in fibonacci_numbers.iter().rev() {
+            if fibonacci_number <= remaining {
+                remaining -= fibonacci_number;
+                smallest_term = fibonacci_number;
+            }
+        }
+
+        smallest_term
+    }
+}
+
+impl NimStrategy for FibonacciStrategy {
+    fn find_winning_move(&self, heaps: &[u32]) -> Option<NimMove> {
+        let heap_index = heaps.iter().position(|&count| count > 0)?;
+        let count_to_remove = Self::smallest_zeckendorf_term(heaps[heap_index]);
+
+        if count_to_remove > self.max_removal {
+            return None;
+        }
+
+        Some(NimMove {
+            heap_index,
+            count_to_remove
+        })
+    }
+
+    fn choose_move(&self, heaps: &[u32], rng: &mut StdRng) -> Option<NimMove> {
+        self.find_winning_move(heaps)
+            .or_else(|| RandomStrategy::new(Some(self.max_removal)).choose_move(heaps, rng))
+    }
+}
+
+/// Winning play for staircase Nim: stones moved off an even step can be
+/// moved straight back down by the opponent, so only the odd steps matter and
+/// they are played as ordinary Nim, moving stones onto the even step below.
+pub struct StaircaseStrategy {
+    max_removal: Option<u32>,
+}
+
+impl StaircaseStrategy {
+    pub fn new(max_removal: Option<u32>) -> StaircaseStrategy {
+        StaircaseStrategy {
+            max_removal
+        }
+    }
+
+    pub fn find_winning_move(&self, steps: &[u32]) -> Option<StaircaseMove> {
+        let odd_steps_xor = steps.iter().skip(1).step_by(2).fold(0, |acc, count| acc ^ count);
+        let step_index = (1..steps.len()).step_by(2)
+            .find(|&step_index| steps[step_index] > (steps[step_index] ^ odd_steps_xor))?;
+        let count_to_move = steps[step_index] - (steps[step_index] ^ odd_steps_xor);
+
+        if self.max_removal.is_some_and(|max_removal| count_to_move > max_removal) {
+            return None;
+        }
+
+        Some(StaircaseMove {
+            step_index,
+            count_to_move
+        })
+    }
+
+    pub fn choose_random_move(&self, steps: &[u32], rng: &mut StdRng) -> Option<StaircaseMove> {
+        let steps_above_floor = steps.get(1..)?;
+
+        RandomStrategy::new(self.max_removal).choose_move(steps_above_floor, rng)
+            .map(|nim_move| StaircaseMove {
+                step_index: nim_move.heap_index + 1,
+                count_to_move: nim_move.count_to_remove
+            })
+    }
+}
+
+impl NimStrategy for StaircaseStrategy {
+    fn find_winning_move(&self, steps: &[u32]) -> Option<NimMove> {
+        StaircaseStrategy::find_winning_move(self, steps)
+            .map(|staircase_move| NimMove {
+                heap_index: staircase_move.step_index,
+                count_to_remove: staircase_move.count_to_move
+            })
+    }
+
+    fn choose_move(&self, steps: &[u32], rng: &mut StdRng) -> Option<NimMove> {
+        NimStrategy::find_winning_move(self, steps).or_else(|| {
+            self.choose_random_move(steps, rng)
+                .map(|staircase_move| NimMove {
+                    heap_index: staircase_move.step_index,
+                    count_to_remove: staircase_move.count_to_move
+                })
+        })
+    }
+}
@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+use super::GameResult;
+use super::system::Player;
+
+pub struct Scoreboard {
+    wins: HashMap<Player, u32>,
+    draws: u32,
+}
+
+impl Scoreboard {
+    pub fn new() -> Scoreboard {
+        Scoreboard {
+            wins: HashMap::new(),
+            draws: 0
+        }
+    }
+    
+    pub fn record(&mut self, result: &GameResult) {
+        match result {
+            GameResult::Win(winner) => *self.wins.entry(winner.clone()).or_insert(0) += 1,
+            GameResult::Draw => self.draws += 1
+        }
+    }
+    
+    pub fn get_wins(&self, player: &Player) -> u32 {
+        self.wins.get(player).copied().unwrap_or(0)
+    }
+    
+    pub fn get_draws(&self) -> u32 {
+        self.draws
+    }
+    
+    pub fn get_match_winner(&self, target_score: Option<u32>) -> Option<Player> {
+        let target_score = target_score?;
+        
+        self.wins.iter()
+            .find(|(_, &wins)| wins >= target_score)
+            .map(|(player, _)| player.clone())
+    }
+}
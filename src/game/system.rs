@@ -1,29 +1,213 @@
 use std::collections::HashMap;
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use rand::{RngCore, SeedableRng};
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
 use sdl2::Sdl;
-use sdl2::event::Event;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::image::LoadTexture;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
-use sdl2::render::WindowCanvas;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Texture, WindowCanvas};
+use sdl2::ttf::Sdl2TtfContext;
+use sdl2::video::FullscreenType;
 
 use std::time::{Duration, Instant};
 use sdl2::rect::Point;
-use super::{NimGame, NimHeap};
+use super::audio::Audio;
+use super::scoreboard::Scoreboard;
+use super::error::GameError;
+use super::text::TextRenderer;
+use super::theme::{step_colour, transition_ratio, Theme};
+use super::{
+    MARGIN_TOP, AiDifficulty, BoardOrientation, GameResult, HeapGame, MoveLimitTiebreak, MoveRecorder, NimGame, NimHeap, NimMove,
+    NimVariant, GreedyStrategy, NimStrategy, OptimalStrategy, RandomStrategy, RecordedGame, ReplayViewer, StaircaseStrategy,
+    SubtractionStrategy, WinningMoveTiebreak, MooreMove, WythoffMove, positions_of_interest
+};
+
+const WINDOW_TITLE: &str = "Nim - the game";
+const LABEL_HEIGHT: u32 = 30;
+const IDLE_DELAY: Duration = Duration::from_secs(2);
+const HINT_PULSE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Fonts borrow the TTF context for as long as they live, so it is created
+/// once and shared by every game.
+static TTF_CONTEXT: OnceLock<Sdl2TtfContext> = OnceLock::new();
+
+fn ttf_context() -> Result<&'static Sdl2TtfContext, String> {
+    if let Some(ttf_context) = TTF_CONTEXT.get() {
+        return Ok(ttf_context);
+    }
+    
+    let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
+    
+    Ok(TTF_CONTEXT.get_or_init(|| ttf_context))
+}
 
 enum GameEvent {
     Quit,
     Other(Event)
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum GameState {
+    Playing,
+    GameOver
+}
+
 pub struct GameSettings {
     pub window_width: u32,
     pub window_height: u32,
     pub microseconds_per_frame: u64,
+    pub idle_microseconds_per_frame: Option<u64>,
+    pub max_catchup_frames: u32,
+    pub frame_pacing: FramePacing,
+    pub show_fps: bool,
+    pub show_nim_value: bool,
+    pub show_starting_outcome: bool,
+    pub fps_smoothing: f64,
     pub microseconds_per_ai_move: u64,
+    pub instant_ai: bool,
+    pub turn_time_limit: Option<Duration>,
+    pub player_one_type: PlayerType,
+    pub player_two_type: PlayerType,
+    pub players_count: u32,
+    pub other_players_type: PlayerType,
+    pub player_one_input: InputDevice,
+    pub player_two_input: InputDevice,
+    pub ai_difficulty: AiDifficulty,
+    pub ai_optimal_move_probability: f64,
+    pub ai_tiebreak: WinningMoveTiebreak,
+    pub input_mode: InputMode,
+    pub release_over_same_heap: bool,
+    pub confirm_removal_threshold: Option<u32>,
+    pub blunder_takebacks: u32,
+    pub double_click_time: Duration,
     pub heaps_count: u32,
+    pub starting_position: Option<Vec<u32>>,
+    pub random_heaps_count: Option<RangeInclusive<u32>>,
     pub max_stones_per_heap: u32,
-    pub target_colour_change_time: Duration
+    pub random_stones_range: Option<RangeInclusive<u32>>,
+    pub target_colour_change_time: Duration,
+    pub board_orientation: BoardOrientation,
+    pub show_empty_slots: bool,
+    pub show_heap_indices: bool,
+    pub remove_from_bottom: bool,
+    pub high_contrast: bool,
+    pub per_heap_colours: bool,
+    pub stone_additions_per_player: u32,
+    pub min_stone_length: u32,
+    pub variant: NimVariant,
+    pub heap_scoring: bool,
+    pub max_moves: Option<u32>,
+    pub history_limit: Option<u32>,
+    pub move_limit_tiebreak: MoveLimitTiebreak,
+    pub max_removal_per_move: Option<u32>,
+    pub max_heaps_per_move: u32,
+    pub heap_games: Vec<HeapGame>,
+    pub seed: Option<u64>,
+    pub state_broadcast: Option<StateBroadcast>,
+    pub background_texture: Option<PathBuf>,
+    pub removal_animation_time: Duration,
+    pub hover_transition_time: Duration,
+    pub match_target_score: Option<u32>,
+    pub save_file: PathBuf,
+    pub move_log: Option<PathBuf>,
+    pub replay_file: Option<PathBuf>,
+    pub replay_viewer_file: Option<PathBuf>,
+    pub simulated_games: Option<u32>,
+    pub move_sound: Option<PathBuf>,
+    pub game_over_sound: Option<PathBuf>,
+    pub sound_volume: f64,
+    pub font_path: PathBuf,
+    pub font_size: u16,
+    pub banner_font_size: u16,
+    pub label_font_size: u16,
+    pub heap_label_colour: Color,
+    pub theme: Theme
+}
+
+impl Default for GameSettings {
+    fn default() -> GameSettings {
+        GameSettings {
+            window_width: 1200,
+            window_height: 800,
+            microseconds_per_frame: 1_000_000 / 60,
+            idle_microseconds_per_frame: None,
+            max_catchup_frames: 5,
+            frame_pacing: FramePacing::Sleep,
+            show_fps: false,
+            show_nim_value: false,
+            show_starting_outcome: false,
+            fps_smoothing: 0.1,
+            microseconds_per_ai_move: 1_000_000 / 2,
+            instant_ai: false,
+            turn_time_limit: None,
+            player_one_type: PlayerType::Human,
+            player_two_type: PlayerType::Computer,
+            players_count: 2,
+            other_players_type: PlayerType::Computer,
+            player_one_input: InputDevice::Any,
+            player_two_input: InputDevice::Any,
+            ai_difficulty: AiDifficulty::Hard,
+            ai_optimal_move_probability: 0.5,
+            ai_tiebreak: WinningMoveTiebreak::LowestIndex,
+            input_mode: InputMode::ClickStone,
+            release_over_same_heap: true,
+            confirm_removal_threshold: None,
+            blunder_takebacks: 0,
+            double_click_time: Duration::from_millis(300),
+            heaps_count: 25,
+            starting_position: None,
+            random_heaps_count: None,
+            max_stones_per_heap: 40,
+            random_stones_range: None,
+            target_colour_change_time: Duration::from_millis(500),
+            board_orientation: BoardOrientation::Vertical,
+            show_empty_slots: false,
+            show_heap_indices: false,
+            remove_from_bottom: false,
+            high_contrast: false,
+            per_heap_colours: false,
+            stone_additions_per_player: 0,
+            min_stone_length: 3,
+            variant: NimVariant::Normal,
+            heap_scoring: false,
+            max_moves: None,
+            history_limit: None,
+            move_limit_tiebreak: MoveLimitTiebreak::Draw,
+            max_removal_per_move: None,
+            max_heaps_per_move: 1,
+            heap_games: Vec::new(),
+            seed: None,
+            state_broadcast: None,
+            background_texture: None,
+            removal_animation_time: Duration::from_millis(250),
+            hover_transition_time: Duration::from_millis(100),
+            match_target_score: None,
+            save_file: PathBuf::from("nim_save.json"),
+            move_log: None,
+            replay_file: None,
+            replay_viewer_file: None,
+            simulated_games: None,
+            move_sound: None,
+            game_over_sound: None,
+            sound_volume: 0.5,
+            font_path: PathBuf::from("assets/font.ttf"),
+            font_size: 32,
+            banner_font_size: 72,
+            label_font_size: 18,
+            heap_label_colour: Color::RGB(255, 255, 255),
+            theme: Theme::default()
+        }
+    }
 }
 
 pub struct MouseState {
@@ -42,30 +226,45 @@ impl Clone for MouseState {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
-pub enum Player {
-    One,
-    Two,
-}
+/// A player identified by their zero-based seat; turns rotate through the
+/// seats in order.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Player(u32);
 
 impl Player {
-    pub fn next(&self) -> Player {
-        match self {
-            Player::One => Player::Two,
-            Player::Two => Player::One,
-        }
+    pub fn new(index: u32) -> Player {
+        Player(index)
+    }
+    
+    pub fn first() -> Player {
+        Player(0)
+    }
+    
+    pub fn all(players_count: u32) -> impl Iterator<Item = Player> {
+        (0..players_count).map(Player)
+    }
+    
+    pub fn index(&self) -> u32 {
+        self.0
+    }
+    
+    pub fn next(&self, players_count: u32) -> Player {
+        Player((self.0 + 1) % players_count)
+    }
+    
+    pub fn previous(&self, players_count: u32) -> Player {
+        Player((self.0 + players_count - 1) % players_count)
     }
 }
 
 impl Display for Player {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        match self {
-            Player::One => write!(f, "Player 1"),
-            Player::Two => write!(f, "Player 2"),
-        }
+        write!(f, "Player {}", self.0 + 1)
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum PlayerType {
     Human,
     Computer
@@ -80,6 +279,61 @@ impl Display for PlayerType {
     }
 }
 
+/// Which controls a human player makes their moves with, so that two people
+/// can share one machine. Input from the other device is ignored on their
+/// turn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputDevice {
+    Any,
+    Mouse,
+    Keyboard
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputMode {
+    ClickStone,
+    TargetCount,
+    Drag
+}
+
+pub struct TargetSelection {
+    pub heap_index: usize,
+    pub target_remaining: u32
+}
+
+struct LastClick {
+    time: Instant,
+    heap_index: usize,
+    moves_count_before: u32,
+    moves_count_after: u32
+}
+
+/// How `wait_to_next_frame` waits out the rest of a frame.
+///
+/// `Sleep` hands the time back to the OS and uses almost no CPU, but wakes up
+/// with scheduler granularity. `Spin` busy-waits for the tightest pacing at the
+/// cost of a fully loaded core. `Hybrid` sleeps for most of the frame and only
+/// spins for the last couple of milliseconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FramePacing {
+    Sleep,
+    Spin,
+    Hybrid
+}
+
+/// Read from the config as `"stdout"` or `{ file = "path" }`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StateBroadcast {
+    Stdout,
+    File(PathBuf)
+}
+
+pub type FrameHook = Box<dyn FnMut(&NimGame, Duration)>;
+
 pub struct Game {
     sdl_context: Sdl,
     canvas: WindowCanvas,
@@ -87,42 +341,186 @@ pub struct Game {
     nim_game: NimGame,
     previous_mouse_state: MouseState,
     current_mouse_state: MouseState,
+    left_press_point: Option<Point>,
+    drag_path: Vec<Point>,
+    shift_held: bool,
+    last_click: Option<LastClick>,
     players: HashMap<Player, PlayerType>,
-    last_human_move_time: Option<Instant>,
+    game_clock: Duration,
+    last_move_time: Duration,
+    paused: bool,
     last_frame_time: Instant,
-    background_colour: Color
+    last_input_time: Instant,
+    frame_budget: Duration,
+    state: GameState,
+    frame_time: Duration,
+    frame_overruns: u32,
+    background_colour: Color,
+    background_texture: Option<Texture>,
+    frame_hook: FrameHook,
+    show_debug_overlay: bool,
+    show_binary_breakdown: bool,
+    show_hint: bool,
+    hint_shown_at: Duration,
+    fullscreen: bool,
+    smoothed_frame_time: Option<f64>,
+    target_selection: Option<TargetSelection>,
+    pending_move: Option<NimMove>,
+    takebacks_left: u32,
+    takeback_offered: bool,
+    moore_selection: Vec<NimMove>,
+    lesson_index: usize,
+    replay_viewer: Option<ReplayViewer>,
+    starting_outcome: (Player, bool),
+    state_broadcaster: Option<Box<dyn Write>>,
+    text_renderer: TextRenderer,
+    scoreboard: Scoreboard,
+    audio: Audio
 }
 
 impl Game {
-    pub fn new(settings: GameSettings) -> Result<Game, String> {
-        let sdl_context = sdl2::init()?;
-        let video_subsystem = sdl_context.video()?;
+    pub fn new(settings: GameSettings) -> Result<Game, GameError> {
+        settings.validate().map_err(GameError::Input)?;
+        
+        let sdl_context = sdl2::init().map_err(GameError::Sdl)?;
+        let video_subsystem = sdl_context.video().map_err(GameError::Sdl)?;
 
         let window = video_subsystem.window(
-            "Nim - the game",
+            WINDOW_TITLE,
             settings.window_width,
             settings.window_height
-        ).position_centered().build()
-            .map_err(|e| e.to_string())?;
+        ).position_centered().resizable().build()
+            .map_err(|e| GameError::Sdl(e.to_string()))?;
 
         let canvas = window
             .into_canvas()
             .build()
-            .map_err(|e| e.to_string())?;
+            .map_err(|e| GameError::Sdl(e.to_string()))?;
+        
+        let ttf_context = ttf_context().map_err(GameError::Sdl)?;
+        let load_font = |size: u16| ttf_context.load_font(&settings.font_path, size);
+        let fonts = load_font(settings.font_size).and_then(|font| {
+            Ok((font, load_font(settings.banner_font_size)?, load_font(settings.label_font_size)?))
+        });
+        let text_renderer = match fonts {
+            Ok((font, banner_font, label_font)) =>
+                TextRenderer::new(canvas.texture_creator(), font, banner_font, label_font),
+            Err(e) => {
+                println!("Failed to load font {}, text is disabled: {}", settings.font_path.display(), e);
+                TextRenderer::disabled(canvas.texture_creator())
+            }
+        };
+        
+        let background_texture = settings.background_texture.as_ref().and_then(|path| {
+            let texture_creator = canvas.texture_creator();
+            
+            match texture_creator.load_texture(path) {
+                Ok(texture) => Some(texture),
+                Err(e) => {
+                    println!("Failed to load background texture {}: {}", path.display(), e);
+                    None
+                }
+            }
+        });
+        
+        let audio = Audio::new(
+            &sdl_context,
+            settings.move_sound.as_ref(),
+            settings.game_over_sound.as_ref(),
+            settings.sound_volume
+        );
+        
+        let state_broadcaster: Option<Box<dyn Write>> = match &settings.state_broadcast {
+            Some(StateBroadcast::Stdout) => Some(Box::new(std::io::stdout())),
+            Some(StateBroadcast::File(path)) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|source| GameError::Io { path: path.clone(), source })?;
+                
+                Some(Box::new(file))
+            },
+            None => None
+        };
         
-        let heaps_count = settings.heaps_count;
+        let heaps_count = match &settings.random_heaps_count {
+            Some(range) => {
+                let min_heaps = *range.start();
+                let max_heaps = *range.end();
+                
+                if min_heaps < 1 || min_heaps > max_heaps {
+                    return Err(GameError::Input(format!("Invalid heaps count range {}..={}", min_heaps, max_heaps)));
+                }
+                
+                let mut rng = StdRng::seed_from_u64(settings.seed.unwrap_or_else(rand::random::<u64>));
+                let heaps_count = min_heaps + rng.next_u32() % (max_heaps - min_heaps + 1);
+                println!("Playing with {} heaps", heaps_count);
+                
+                heaps_count
+            },
+            None => settings.heaps_count
+        };
+        let heaps_count = match settings.variant {
+            NimVariant::Wythoff if heaps_count != 2 => {
+                println!("Wythoff's game is played on two heaps");
+                2
+            },
+            NimVariant::Fibonacci if heaps_count != 1 => {
+                println!("Fibonacci Nim is played on a single heap");
+                1
+            },
+            _ => heaps_count
+        };
         let max_stones_per_heap = settings.max_stones_per_heap;
 
         let default_heap = NimHeap::new(max_stones_per_heap, 10);
-        let mut nim_game = NimGame::new(default_heap);
+        let mut nim_game = match &settings.starting_position {
+            Some(counts) => {
+                let max_heaps = match settings.variant {
+                    NimVariant::Wythoff => 2,
+                    NimVariant::Fibonacci => 1,
+                    _ => counts.len()
+                };
+                
+                NimGame::from_position(default_heap, counts, max_heaps)
+                    .map_err(|e| GameError::Input(e.to_string()))?
+            },
+            None => NimGame::new(default_heap)
+        };
+        Self::configure_nim_game(&mut nim_game, &settings);
 
-        for _ in 0..heaps_count {
-            nim_game.add_random_heap();
+        if settings.starting_position.is_none() {
+            for _ in 0..heaps_count {
+                nim_game.add_random_heap();
+            }
+        }
+        
+        let replay_viewer = settings.replay_viewer_file.as_deref()
+            .map(ReplayViewer::load)
+            .transpose()
+            .map_err(GameError::Input)?;
+        
+        if let Some(replay_viewer) = &replay_viewer {
+            nim_game = Self::create_replay_game(replay_viewer.current_game(), &settings)
+                .map_err(GameError::Input)?;
+        } else if let Some(path) = &settings.move_log {
+            let recorder = MoveRecorder::create(path)
+                .map_err(|source| GameError::Io { path: path.clone(), source })?;
+            
+            nim_game.set_recorder(Some(recorder));
         }
         
-        let players =
-            vec![(Player::One, PlayerType::Human), (Player::Two, PlayerType::Computer)]
-            .into_iter()
+        let players = Player::all(settings.players_count)
+            .map(|player| {
+                let player_type = match player.index() {
+                    0 => settings.player_one_type,
+                    1 => settings.player_two_type,
+                    _ => settings.other_players_type
+                };
+                
+                (player, player_type)
+            })
             .collect::<HashMap<Player, PlayerType>>();
         
         let current_mouse_state = MouseState {
@@ -132,6 +530,9 @@ impl Game {
         };
         
         let previous_mouse_state = current_mouse_state.clone();
+        let starting_outcome = Self::get_starting_outcome(&nim_game);
+        let frame_budget = Duration::from_micros(settings.microseconds_per_frame);
+        let takebacks_left = settings.blunder_takebacks;
 
         Ok(Game {
             sdl_context,
@@ -140,31 +541,137 @@ impl Game {
             nim_game,
             previous_mouse_state,
             current_mouse_state,
+            left_press_point: None,
+            drag_path: Vec::new(),
+            shift_held: false,
+            last_click: None,
             players,
-            last_human_move_time: None,
+            game_clock: Duration::ZERO,
+            last_move_time: Duration::ZERO,
+            paused: false,
             last_frame_time: Instant::now(),
-            background_colour: Color::RGB(0, 0, 155)
+            last_input_time: Instant::now(),
+            frame_budget,
+            state: GameState::Playing,
+            frame_time: Duration::ZERO,
+            frame_overruns: 0,
+            background_colour: Color::RGB(0, 0, 155),
+            background_texture,
+            frame_hook: Box::new(|_, _| {}),
+            show_debug_overlay: false,
+            show_binary_breakdown: false,
+            show_hint: false,
+            hint_shown_at: Duration::ZERO,
+            fullscreen: false,
+            smoothed_frame_time: None,
+            target_selection: None,
+            pending_move: None,
+            takebacks_left,
+            takeback_offered: false,
+            moore_selection: Vec::new(),
+            lesson_index: 0,
+            replay_viewer,
+            starting_outcome,
+            state_broadcaster,
+            text_renderer,
+            scoreboard: Scoreboard::new(),
+            audio
         })
     }
 
-    pub fn run(&mut self) -> Result<(), String> {
-        let mut event_pump = self.sdl_context.event_pump()?;
+    fn configure_nim_game(nim_game: &mut NimGame, settings: &GameSettings) {
+        Self::configure_board_drawing(nim_game, settings);
+        nim_game.set_players_count(settings.players_count);
+        nim_game.set_additions_per_player(settings.stone_additions_per_player);
+        nim_game.set_random_stones_range(settings.random_stones_range.clone());
+        nim_game.set_variant(settings.variant);
+        nim_game.set_ai_difficulty(settings.ai_difficulty, settings.ai_optimal_move_probability);
+        nim_game.set_strategy(Self::create_strategy(settings));
+        nim_game.set_heap_scoring(settings.heap_scoring);
+        nim_game.set_move_limit(settings.max_moves, settings.move_limit_tiebreak);
+        nim_game.set_max_removal_per_move(settings.max_removal_per_move);
+        nim_game.set_max_heaps_per_move(settings.max_heaps_per_move);
+        nim_game.set_heap_games(settings.heap_games.clone());
+        nim_game.set_history_limit(settings.history_limit);
+        
+        if let Some(seed) = settings.seed {
+            nim_game.set_seed(seed);
+        }
+    }
+
+    fn configure_board_drawing(nim_game: &mut NimGame, settings: &GameSettings) {
+        nim_game.set_orientation(settings.board_orientation);
+        nim_game.set_remove_from_bottom(settings.remove_from_bottom);
+        nim_game.set_show_empty_slots(settings.show_empty_slots);
+        nim_game.set_min_stone_length(settings.min_stone_length);
+        nim_game.set_high_contrast(settings.high_contrast);
+        nim_game.set_per_heap_colours(settings.per_heap_colours);
+        nim_game.set_theme(settings.theme);
+        nim_game.set_removal_animation_time(settings.removal_animation_time);
+        nim_game.set_hover_transition_time(settings.hover_transition_time);
+    }
+    
+    /// A recorded game keeps the rules taken from its log; only the way the
+    /// board is drawn comes from the settings.
+    fn create_replay_game(recorded_game: &RecordedGame, settings: &GameSettings) -> Result<NimGame, String> {
+        let default_heap = NimHeap::new(settings.max_stones_per_heap, 10);
+        let mut nim_game = NimGame::from_recording(default_heap, recorded_game)?;
+        
+        Self::configure_board_drawing(&mut nim_game, settings);
+        
+        Ok(nim_game)
+    }
+
+    fn create_strategy(settings: &GameSettings) -> Box<dyn NimStrategy> {
+        if settings.variant == NimVariant::Staircase {
+            return Box::new(StaircaseStrategy::new(settings.max_removal_per_move));
+        }
+        
+        if settings.heap_scoring {
+            return Box::new(GreedyStrategy::new(settings.max_removal_per_move));
+        }
+        
+        match settings.max_removal_per_move {
+            Some(max_removal) => Box::new(SubtractionStrategy::new(max_removal)),
+            None => Box::new(OptimalStrategy::new(settings.variant, settings.ai_tiebreak))
+        }
+    }
+
+    pub fn set_frame_hook<F>(&mut self, frame_hook: F)
+    where
+        F: FnMut(&NimGame, Duration) + 'static
+    {
+        self.frame_hook = Box::new(frame_hook);
+    }
+
+    pub fn run(&mut self) -> Result<(), GameError> {
+        let mut event_pump = self.sdl_context.event_pump().map_err(GameError::Sdl)?;
+        self.broadcast_state();
 
         'running: loop {
             let start_time = Instant::now();
+            self.begin_frame();
 
             for event in event_pump.poll_iter() {
+                self.last_input_time = Instant::now();
+                
                 match self.handle_event(event) {
                     GameEvent::Quit => break 'running,
                     GameEvent::Other(_) => {}
                 }
             }
         
-            self.handle_ai_players();
+            if self.state == GameState::Playing && !self.is_replaying() {
+                self.handle_ai_players();
+                self.handle_turn_timeout();
+            }
+            
+            (self.frame_hook)(&self.nim_game, self.frame_time);
+            self.nim_game.advance_animations(self.frame_time);
             self.draw_frame()?;
             
-            if self.handle_game_ending() {
-                break 'running;
+            if self.state == GameState::Playing && !self.is_replaying() && self.handle_game_ending() {
+                self.state = GameState::GameOver;
             }
             
             self.wait_to_next_frame(start_time);
@@ -174,15 +681,44 @@ impl Game {
     }
     
     fn handle_game_ending(&mut self) -> bool {
-        if self.nim_game.is_game_over() {
-            let player_to_move = self.nim_game.get_player_to_move();
-            let winner = player_to_move.next();
-            
+        if let Some(result) = self.nim_game.core().get_result() {
             println!("Game over!");
-            println!("{} wins!", winner);
+            self.audio.play_game_over_sound();
+            
+            if self.nim_game.core().is_move_limit_reached() {
+                println!("Move limit of {} moves reached", self.nim_game.core().get_moves_count());
+            }
+            
+            if self.nim_game.core().is_heap_scoring() {
+                let points = Player::all(self.settings.players_count)
+                    .map(|player| format!("{}: {} points", player, self.nim_game.core().get_points(&player)))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                
+                println!("{}", points);
+            }
+            
+            match &result {
+                GameResult::Win(winner) => {
+                    println!("{} wins!", winner);
 
-            if let Some(winner_type) = self.players.get(&winner) {
-                println!("This player is a {}", winner_type);
+                    if let Some(winner_type) = self.players.get(winner) {
+                        println!("This player is a {}", winner_type);
+                    }
+                },
+                GameResult::Draw => println!("It's a draw!")
+            }
+            
+            self.scoreboard.record(&result);
+            let score = Player::all(self.settings.players_count)
+                .map(|player| format!("{} {}", player, self.scoreboard.get_wins(&player)))
+                .collect::<Vec<String>>()
+                .join(" - ");
+            
+            println!("Score: {} ({} draws)", score, self.scoreboard.get_draws());
+            
+            if let Some(match_winner) = self.get_match_winner() {
+                println!("{} wins the match!", match_winner);
             }
             
             return true;
@@ -191,173 +727,1547 @@ impl Game {
         false
     }
     
-    fn handle_ai_players(&mut self) {
-        if let Some(last_human_move_time) = self.last_human_move_time {
-            let elapsed_time = last_human_move_time.elapsed();
-            let elapsed_micros = elapsed_time.as_micros() as u64;
-            
-            if elapsed_micros >= self.settings.microseconds_per_ai_move {
-                self.handle_ai_move();
-            }
+    fn get_match_winner(&self) -> Option<Player> {
+        self.scoreboard.get_match_winner(self.settings.match_target_score)
+    }
+    
+    fn exponential_moving_average(previous: Option<f64>, sample: f64, smoothing: f64) -> f64 {
+        match previous {
+            Some(previous) => previous + smoothing.clamp(0.0, 1.0) * (sample - previous),
+            None => sample
         }
     }
     
-    fn handle_player_move(&mut self) {
-        let player_to_move = self.nim_game.get_player_to_move();
+    fn update_frame_time(&mut self, frame_time: Duration) {
+        self.smoothed_frame_time = Some(Self::exponential_moving_average(
+            self.smoothed_frame_time,
+            frame_time.as_secs_f64(),
+            self.settings.fps_smoothing
+        ));
+    }
+    
+    fn is_replaying(&self) -> bool {
+        self.replay_viewer.is_some()
+    }
+    
+    fn step_replay(&mut self, forward: bool) {
+        let replay_viewer = match &mut self.replay_viewer {
+            Some(replay_viewer) => replay_viewer,
+            None => return
+        };
         
-        if let Some(PlayerType::Human) = self.players.get(player_to_move) {
-            let point = self.current_mouse_state.point;
-            let nim_move_option = self.nim_game.prepare_player_move(point);
-            
-            if let Some(nim_move) = nim_move_option {
-                self.nim_game.make_move(nim_move);
-                self.last_human_move_time = Some(Instant::now());
-            }
+        let stepped = if forward {
+            replay_viewer.step_forward(&mut self.nim_game)
+        } else {
+            replay_viewer.step_backward(&mut self.nim_game)
+        };
+        
+        if stepped {
+            self.handle_position_change();
         }
     }
     
-    fn handle_ai_move(&mut self) {
-        let player_to_move = self.nim_game.get_player_to_move();
+    fn select_replay_game(&mut self, offset: i64) {
+        let replay_viewer = match &mut self.replay_viewer {
+            Some(replay_viewer) => replay_viewer,
+            None => return
+        };
         
-        if let Some(PlayerType::Computer) = self.players.get(player_to_move) {
-            let nim_move_option = self.nim_game.prepare_ai_move();
-            
-            if let Some(nim_move) = nim_move_option {
-                self.nim_game.make_move(nim_move);
-                self.last_human_move_time = None;
+        let game_index = replay_viewer.get_game_index() as i64 + offset;
+        
+        if game_index < 0 || !replay_viewer.select_game(game_index as usize) {
+            return;
+        }
+        
+        match Self::create_replay_game(replay_viewer.current_game(), &self.settings) {
+            Ok(nim_game) => {
+                self.nim_game = nim_game;
+                self.starting_outcome = Self::get_starting_outcome(&self.nim_game);
+                self.handle_position_change();
+            },
+            Err(e) => println!("Failed to show game {}: {}", game_index + 1, e)
+        }
+    }
+    
+    fn handle_potential_replay_key_down(&mut self, event: &Event) {
+        if let Event::KeyDown { keycode: Some(keycode), repeat, .. } = event {
+            match keycode {
+                Keycode::Left => self.step_replay(false),
+                Keycode::Right => self.step_replay(true),
+                Keycode::Up if !repeat => self.select_replay_game(-1),
+                Keycode::Down if !repeat => self.select_replay_game(1),
+                Keycode::F if !repeat => self.toggle_fullscreen(),
+                Keycode::B if !repeat => self.show_binary_breakdown = !self.show_binary_breakdown,
+                _ => {}
             }
         }
     }
     
-    fn move_mouse_states(&mut self) {
-        self.previous_mouse_state = self.current_mouse_state.clone();
+    fn is_paused(&self) -> bool {
+        self.paused
     }
     
-    fn handle_potential_mouse_moved(&mut self, event: &Event) {
-        if let Event::MouseMotion { x, y, .. } = event {
-            self.current_mouse_state.point = Point::new(*x, *y);
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        
+        if self.paused {
+            println!("Game paused");
+        } else {
+            println!("Game resumed");
         }
     }
     
-    fn handle_left_click_up(&mut self) {
-        self.handle_player_move();
+    /// Game time spent on the current turn. The game clock only advances on
+    /// unpaused frames, by at most the capped frame time.
+    fn time_since_last_move(&self) -> Duration {
+        self.game_clock.saturating_sub(self.last_move_time)
     }
     
-    fn handle_potential_mouse_button(&mut self, event: &Event) {
-        match event {
-            Event::MouseButtonDown {..} | Event::MouseButtonUp {..} => {
-                self.move_mouse_states();
-                
-                match event {
-                    Event::MouseButtonDown { mouse_btn, .. } => {
-                        match mouse_btn {
-                            sdl2::mouse::MouseButton::Left => self.current_mouse_state.left_button = true,
-                            sdl2::mouse::MouseButton::Right => self.current_mouse_state.right_button = true,
-                            _ => {}
-                        }
-                    },
-                    Event::MouseButtonUp { mouse_btn, .. } => {
-                        match mouse_btn {
-                            sdl2::mouse::MouseButton::Left => {
-                                self.current_mouse_state.left_button = false;
-                                self.handle_left_click_up();
-                            },
-                            sdl2::mouse::MouseButton::Right => self.current_mouse_state.right_button = false,
-                            _ => {}
-                        }
-                    },
-                    _ => {}
-                }
-            },
-            _ => {}
+    fn handle_ai_players(&mut self) {
+        if self.is_paused() || self.takeback_offered || !self.is_computer_to_move() {
+            return;
+        }
+        
+        let elapsed_micros = self.time_since_last_move().as_micros() as u64;
+        
+        if elapsed_micros >= self.get_microseconds_per_ai_move() {
+            self.handle_ai_move();
         }
     }
-
-    fn handle_event(&mut self, event: Event) -> GameEvent {
-        match event {
-            Event::Quit {..} |
-            Event::KeyDown { keycode: Some(Keycode::Escape), .. } => GameEvent::Quit,
-            _ => {
-                self.handle_potential_mouse_moved(&event);
-                self.handle_potential_mouse_button(&event);
-                
-                GameEvent::Other(event)
-            }
+    
+    fn get_microseconds_per_ai_move(&self) -> u64 {
+        if self.settings.instant_ai {
+            0
+        } else {
+            self.settings.microseconds_per_ai_move
         }
     }
     
-    fn draw_background(&mut self) {
-        fn subtract_colour(colour1: Color, colour2: Color) -> (f64, f64, f64) {
-            let r1 = colour1.r as f64;
-            let g1 = colour1.g as f64;
-            let b1 = colour1.b as f64;
-            
-            let r2 = colour2.r as f64;
-            let g2 = colour2.g as f64;
-            let b2 = colour2.b as f64;
-            
-            (r1 - r2, g1 - g2, b1 - b2)
+    fn progress_fraction(elapsed_micros: u64, total_micros: u64) -> f64 {
+        if total_micros == 0 {
+            return 1.0;
         }
         
-        fn multiply_colour((r, g, b): (f64, f64, f64), factor: f64) -> (f64, f64, f64) {
-            (r * factor, g * factor, b * factor)
+        (elapsed_micros as f64 / total_micros as f64).min(1.0)
+    }
+    
+    /// How far the computer is through its move delay, from 0.0 to 1.0.
+    fn ai_thinking_progress(&self) -> Option<f64> {
+        if !self.is_computer_to_move() || self.nim_game.core().is_game_over() {
+            return None;
         }
         
-        fn add_colour(colour1: Color, (r, g, b): (f64, f64, f64)) -> Color {
-            let r = (colour1.r as f64 + r) as u8;
-            let g = (colour1.g as f64 + g) as u8;
-            let b = (colour1.b as f64 + b) as u8;
-            
-            Color::RGB(r, g, b)
+        let elapsed_micros = self.time_since_last_move().as_micros() as u64;
+        
+        Some(Self::progress_fraction(elapsed_micros, self.get_microseconds_per_ai_move()))
+    }
+    
+    fn remaining_turn_time(&self) -> Option<Duration> {
+        let turn_time_limit = self.settings.turn_time_limit?;
+        
+        if !self.is_human_to_move() || self.nim_game.core().is_game_over() {
+            return None;
         }
         
-        let first_player_background_colour = Color::RGB(100, 155, 0);
-        let second_player_background_colour = Color::RGB(155, 100, 0);
-        let current_player = self.nim_game.get_player_to_move();
+        Some(turn_time_limit.saturating_sub(self.time_since_last_move()))
+    }
+    
+    fn handle_turn_timeout(&mut self) {
+        if self.remaining_turn_time() != Some(Duration::ZERO) {
+            return;
+        }
         
-        let time_since_last_frame = self.last_frame_time.elapsed();
-        let target_colour_change_time = self.settings.target_colour_change_time;
+        println!("{} ran out of time", self.nim_game.core().get_player_to_move());
         
-        let time_ratio = time_since_last_frame.as_secs_f64() / target_colour_change_time.as_secs_f64();
-                
-        self.background_colour =
-            add_colour(
-                self.background_colour,
-                multiply_colour(
-                    subtract_colour(
-                        match current_player {
-                            Player::One => first_player_background_colour,
-                            Player::Two => second_player_background_colour
-                        },
-                        self.background_colour
-                    ),
-                    time_ratio
-                )
-            );
-        
-        self.canvas.set_draw_color(self.background_colour);
-        self.canvas.clear();
-    }
-
-    fn draw_frame(&mut self) -> Result<(), String> {
-        self.draw_background();
-        self.nim_game.draw_board(&mut self.canvas, &self.current_mouse_state)?;
-
-        self.canvas.present();
-
-        Ok(())
+        if let Some(nim_move) = self.nim_game.core().prepare_smallest_move() {
+            self.target_selection = None;
+            self.make_human_move(nim_move);
+        }
     }
-
-    fn wait_to_next_frame(&mut self, start_time: Instant) {
-        self.last_frame_time = Instant::now();
+    
+    fn is_computer_to_move(&self) -> bool {
+        let player_to_move = self.nim_game.core().get_player_to_move();
         
-        let elapsed_time = start_time.elapsed();
-        let elapsed_micros = elapsed_time.as_micros() as i64;
-        let remaining_micros = self.settings.microseconds_per_frame as i64 - elapsed_micros;
-
-        if remaining_micros > 0 {
-            let remaining_duration = Duration::from_micros(remaining_micros as u64);
-            ::std::thread::sleep(remaining_duration);
+        matches!(self.players.get(player_to_move), Some(PlayerType::Computer))
+    }
+    
+    fn has_human_player(&self) -> bool {
+        self.players.values().any(|player_type| *player_type == PlayerType::Human)
+    }
+    
+    /// Hands the side to move over to the computer, or takes it back. The
+    /// computer's move delay starts counting from the swap.
+    fn swap_sides(&mut self) {
+        if self.nim_game.core().is_game_over() {
+            return;
+        }
+        
+        let player = self.nim_game.core().get_player_to_move().clone();
+        let player_type = match self.players.get(&player) {
+            Some(PlayerType::Human) => PlayerType::Computer,
+            _ => PlayerType::Human
+        };
+        
+        println!("{} is now played by {}", player, player_type);
+        self.players.insert(player, player_type);
+        
+        self.handle_position_change();
+    }
+    
+    fn is_human_to_move(&self) -> bool {
+        let player_to_move = self.nim_game.core().get_player_to_move();
+        
+        matches!(self.players.get(player_to_move), Some(PlayerType::Human))
+    }
+    
+    fn input_device_to_move(&self) -> InputDevice {
+        match self.nim_game.core().get_player_to_move().index() {
+            0 => self.settings.player_one_input,
+            1 => self.settings.player_two_input,
+            _ => InputDevice::Any
+        }
+    }
+    
+    fn accepts_input_from(&self, input_device: InputDevice) -> bool {
+        let player_input = self.input_device_to_move();
+        
+        player_input == InputDevice::Any || player_input == input_device
+    }
+    
+    fn make_human_move(&mut self, nim_move: NimMove) {
+        if self.is_paused() {
+            return;
+        }
+        
+        self.pending_move = None;
+        
+        match self.nim_game.make_move(nim_move) {
+            Ok(()) => self.finish_human_move(),
+            Err(e) => println!("Illegal move: {}", e)
+        }
+    }
+    
+    fn make_human_diagonal_move(&mut self, count_to_remove: u32) {
+        if self.is_paused() {
+            return;
+        }
+        
+        self.pending_move = None;
+        
+        if self.nim_game.make_wythoff_move(&WythoffMove::Both { count_to_remove }) {
+            self.finish_human_move();
+        } else {
+            println!("Illegal move: both heaps need {} stones", count_to_remove);
+        }
+    }
+    
+    fn finish_human_move(&mut self) {
+        self.audio.play_move_sound();
+        self.show_hint = false;
+        self.last_move_time = self.game_clock;
+        self.broadcast_state();
+        self.offer_potential_takeback();
+    }
+    
+    /// After a move that throws away a win against the computer, holds the
+    /// computer's reply until the player takes the move back or keeps it.
+    fn offer_potential_takeback(&mut self) {
+        if self.takebacks_left == 0 || !self.is_computer_to_move() || !self.nim_game.is_last_move_blunder() {
+            return;
+        }
+        
+        self.takeback_offered = true;
+        println!("That move gives away the win: press U to take it back or Enter to keep it");
+    }
+    
+    fn take_back_blunder(&mut self) {
+        self.takeback_offered = false;
+        
+        if self.nim_game.undo_last_move() {
+            self.takebacks_left -= 1;
+            println!("Move taken back, {} takebacks left this game", self.takebacks_left);
+            
+            self.handle_position_change();
+        }
+    }
+    
+    fn keep_blunder(&mut self) {
+        self.takeback_offered = false;
+        self.last_move_time = self.game_clock;
+    }
+    
+    fn handle_potential_takeback_key_down(&mut self, event: &Event) {
+        if let Event::KeyDown { keycode: Some(keycode), repeat: false, .. } = event {
+            match keycode {
+                Keycode::U => self.take_back_blunder(),
+                Keycode::Return => self.keep_blunder(),
+                Keycode::F => self.toggle_fullscreen(),
+                _ => {}
+            }
         }
     }
-}
\ No newline at end of file
+    
+    /// A Shift-click in Wythoff's game mirrors the removal onto the other
+    /// heap, taking the same number of stones from both. In Moore's Nim it
+    /// adds the removal to the heaps taken from together.
+    fn handle_shift_clicked_move(&mut self, nim_move: NimMove) -> bool {
+        if self.nim_game.core().get_variant() == NimVariant::Wythoff {
+            self.make_human_diagonal_move(nim_move.count_to_remove);
+            
+            return true;
+        }
+        
+        if self.nim_game.core().get_max_heaps_per_move() > 1 {
+            self.select_moore_removal(nim_move);
+            
+            return true;
+        }
+        
+        false
+    }
+    
+    /// Another Shift-click on an already selected heap replaces its removal.
+    fn select_moore_removal(&mut self, nim_move: NimMove) {
+        let max_heaps = self.nim_game.core().get_max_heaps_per_move();
+        
+        self.moore_selection.retain(|removal| removal.heap_index != nim_move.heap_index);
+        
+        if self.moore_selection.len() as u32 >= max_heaps {
+            println!("A move takes stones from at most {} heaps", max_heaps);
+            return;
+        }
+        
+        self.moore_selection.push(nim_move);
+        println!("{} heaps selected, press Enter to take the stones", self.moore_selection.len());
+    }
+    
+    fn commit_moore_selection(&mut self) -> bool {
+        if self.moore_selection.is_empty() {
+            return false;
+        }
+        
+        if self.is_paused() {
+            return true;
+        }
+        
+        let moore_move = MooreMove {
+            removals: std::mem::take(&mut self.moore_selection)
+        };
+        
+        self.make_human_moore_move(&moore_move);
+        
+        true
+    }
+    
+    fn make_human_moore_move(&mut self, moore_move: &MooreMove) {
+        if self.is_paused() {
+            return;
+        }
+        
+        self.pending_move = None;
+        
+        if self.nim_game.make_moore_move(moore_move) {
+            self.finish_human_move();
+        } else {
+            println!("Illegal move: the selected removals can't be played together");
+        }
+    }
+    
+    /// Poker Nim: puts one stone back on the hovered heap, or on the selected
+    /// one for a keyboard player, using up one of the player's additions.
+    /// The computer never adds stones.
+    fn add_human_stone(&mut self) {
+        if self.is_paused() || !self.is_human_to_move() {
+            return;
+        }
+        
+        let heap_index = match self.input_device_to_move() {
+            InputDevice::Keyboard => self.target_selection.as_ref().map(|selection| selection.heap_index),
+            InputDevice::Any | InputDevice::Mouse => self.nim_game.get_heap_index_at(self.current_mouse_state.point)
+        };
+        let heap_index = match heap_index {
+            Some(heap_index) => heap_index,
+            None => return
+        };
+        
+        match self.nim_game.add_stones(heap_index, 1) {
+            Ok(()) => {
+                let player = self.nim_game.core().get_player_to_move().previous(self.settings.players_count);
+                
+                println!("{} added a stone, {} additions left", player, self.nim_game.get_additions_left(&player));
+                self.audio.play_move_sound();
+                self.handle_position_change();
+            },
+            Err(e) => println!("Can't add a stone: {}", e)
+        }
+    }
+    
+    /// Removals above the confirmation threshold wait for a second click on
+    /// the same stone or Enter; any other click cancels them.
+    fn handle_clicked_move(&mut self, nim_move: Option<NimMove>) {
+        if let Some(nim_move) = nim_move.filter(|_| self.shift_held) {
+            if self.handle_shift_clicked_move(nim_move) {
+                return;
+            }
+        }
+        
+        if !self.moore_selection.is_empty() {
+            self.moore_selection.clear();
+            println!("Selection cancelled");
+            
+            return;
+        }
+        
+        if let Some(pending_move) = self.pending_move.take() {
+            if nim_move == Some(pending_move) {
+                self.make_human_move(pending_move);
+            } else {
+                println!("Removal cancelled");
+            }
+            
+            return;
+        }
+        
+        let nim_move = match nim_move {
+            Some(nim_move) => nim_move,
+            None => return
+        };
+        
+        let needs_confirmation = self.nim_game.is_legal_move(&nim_move) && self.settings.confirm_removal_threshold
+            .is_some_and(|threshold| nim_move.count_to_remove > threshold);
+        
+        if needs_confirmation {
+            println!("Click again or press Enter to remove {} stones", nim_move.count_to_remove);
+            self.pending_move = Some(nim_move);
+        } else {
+            self.make_human_move(nim_move);
+        }
+    }
+    
+    fn confirm_pending_move(&mut self) -> bool {
+        match self.pending_move.take() {
+            Some(pending_move) => {
+                self.make_human_move(pending_move);
+                true
+            },
+            None => false
+        }
+    }
+    
+    /// Enter plays the removal waiting for confirmation, then a Moore's Nim
+    /// selection, then the keyboard target selection.
+    fn commit_selected_move(&mut self) {
+        if !self.confirm_pending_move() && !self.commit_moore_selection() {
+            self.commit_target_selection();
+        }
+    }
+    
+    /// The pulse starts over each time the hint is shown; any change of
+    /// position hides the hint.
+    fn toggle_hint(&mut self) {
+        self.show_hint = !self.show_hint && self.is_human_to_move();
+        self.hint_shown_at = self.game_clock;
+    }
+    
+    /// Goes from 0.0 to 1.0 and back once every `HINT_PULSE_PERIOD`.
+    fn hint_pulse(&self) -> f64 {
+        let elapsed = self.game_clock.saturating_sub(self.hint_shown_at).as_secs_f64();
+        let phase = elapsed / HINT_PULSE_PERIOD.as_secs_f64() * std::f64::consts::TAU;
+        
+        (1.0 - phase.cos()) / 2.0
+    }
+    
+    fn undo_move(&mut self) {
+        if !self.nim_game.undo_last_move() {
+            return;
+        }
+        
+        while self.has_human_player() && !self.is_human_to_move() && self.nim_game.core().can_undo() {
+            self.nim_game.undo_last_move();
+        }
+        
+        self.handle_position_change();
+    }
+    
+    fn redo_move(&mut self) {
+        if !self.nim_game.redo_move() {
+            return;
+        }
+        
+        while self.has_human_player() && !self.is_human_to_move() && self.nim_game.core().can_redo() {
+            self.nim_game.redo_move();
+        }
+        
+        self.handle_position_change();
+    }
+    
+    /// Leaves the game over screen; after a won match the scores start over.
+    fn play_again(&mut self) {
+        if self.get_match_winner().is_some() {
+            self.scoreboard = Scoreboard::new();
+            println!("New match started");
+        }
+        
+        self.state = GameState::Playing;
+        self.reset_game();
+    }
+    
+    /// Skips the rest of a game between computers; the result is shown on
+    /// the next frame.
+    fn fast_forward_to_result(&mut self) {
+        if self.has_human_player() || self.nim_game.core().is_game_over() {
+            return;
+        }
+        
+        let moves_count = self.nim_game.play_out_ai_moves();
+        println!("Fast-forwarded {} moves", moves_count);
+        
+        self.handle_position_change();
+    }
+    
+    fn reset_game(&mut self) {
+        self.nim_game.reset();
+        self.takebacks_left = self.settings.blunder_takebacks;
+        self.starting_outcome = Self::get_starting_outcome(&self.nim_game);
+        println!("New game started");
+        
+        self.handle_position_change();
+    }
+    
+    fn save_game(&mut self) {
+        match self.nim_game.save_to_file(&self.settings.save_file) {
+            Ok(()) => println!("Game saved to {}", self.settings.save_file.display()),
+            Err(e) => println!("Failed to save game: {}", e)
+        }
+    }
+    
+    fn load_game(&mut self) {
+        match NimGame::load_from_file(&self.settings.save_file) {
+            Ok(mut nim_game) => {
+                Self::configure_nim_game(&mut nim_game, &self.settings);
+                nim_game.set_recorder(self.nim_game.take_recorder());
+                self.nim_game = nim_game;
+                self.takebacks_left = self.settings.blunder_takebacks;
+                self.starting_outcome = Self::get_starting_outcome(&self.nim_game);
+                println!("Game loaded from {}", self.settings.save_file.display());
+                
+                self.handle_position_change();
+            },
+            Err(e) => println!("Failed to load game: {}", e)
+        }
+    }
+    
+    /// Starts the next of the instructive positions for the configured heap
+    /// count as a new game, going back to the first after the last one.
+    fn start_next_lesson(&mut self) {
+        if self.settings.variant != NimVariant::Normal {
+            println!("Lessons are played with the normal rules");
+            return;
+        }
+        
+        let positions = positions_of_interest(
+            self.settings.heaps_count,
+            self.settings.max_stones_per_heap,
+            self.settings.seed.unwrap_or_default()
+        );
+        let position = match positions.get(self.lesson_index % positions.len().max(1)) {
+            Some(position) => position,
+            None => return
+        };
+        let default_heap = NimHeap::new(self.settings.max_stones_per_heap, 10);
+        
+        match NimGame::from_position(default_heap, &position.counts, position.counts.len()) {
+            Ok(mut nim_game) => {
+                Self::configure_nim_game(&mut nim_game, &self.settings);
+                nim_game.set_recorder(self.nim_game.take_recorder());
+                self.nim_game = nim_game;
+                self.takebacks_left = self.settings.blunder_takebacks;
+                self.starting_outcome = Self::get_starting_outcome(&self.nim_game);
+                self.lesson_index += 1;
+                println!("Lesson: {}", position.label);
+                
+                self.handle_position_change();
+            },
+            Err(e) => println!("Failed to start the lesson: {}", e)
+        }
+    }
+    
+    fn handle_position_change(&mut self) {
+        self.target_selection = None;
+        self.pending_move = None;
+        self.takeback_offered = false;
+        self.moore_selection.clear();
+        self.show_hint = false;
+        self.last_move_time = self.game_clock;
+        
+        self.broadcast_state();
+    }
+    
+    fn broadcast_state(&mut self) {
+        if let Some(state_broadcaster) = &mut self.state_broadcaster {
+            if let Err(e) = writeln!(state_broadcaster, "{}", self.nim_game) {
+                println!("Failed to broadcast game state: {}", e);
+            }
+        }
+    }
+    
+    fn handle_player_move(&mut self, drag_path: &[Point]) {
+        if !self.is_human_to_move() {
+            return;
+        }
+        
+        let point = self.current_mouse_state.point;
+        
+        match self.settings.input_mode {
+            InputMode::ClickStone => {
+                let nim_move = self.nim_game.prepare_player_move(point);
+                
+                self.handle_clicked_move(nim_move);
+            },
+            InputMode::TargetCount => {
+                self.target_selection = self.nim_game.get_heap_index_at(point)
+                    .and_then(|heap_index| {
+                        let heap_count = self.nim_game.core().get_heap_count(heap_index)?;
+                        
+                        if heap_count == 0 {
+                            return None;
+                        }
+                        
+                        Some(TargetSelection {
+                            heap_index,
+                            target_remaining: heap_count - 1
+                        })
+                    });
+            },
+            InputMode::Drag => {
+                let max_heaps = self.nim_game.core().get_max_heaps_per_move();
+                let mut removals = self.nim_game.prepare_drag_move(drag_path, max_heaps);
+                
+                if removals.len() > 1 {
+                    self.make_human_moore_move(&MooreMove { removals });
+                } else {
+                    self.handle_clicked_move(removals.pop());
+                }
+            }
+        }
+    }
+    
+    fn adjust_target_remaining(&mut self, delta: i32) {
+        if let Some(selection) = &mut self.target_selection {
+            let heap_count = self.nim_game.core().get_heap_count(selection.heap_index).unwrap_or(0);
+            let max_remaining = heap_count.saturating_sub(1) as i64;
+            let target_remaining = (selection.target_remaining as i64 + delta as i64).clamp(0, max_remaining);
+            
+            selection.target_remaining = target_remaining as u32;
+        }
+    }
+    
+    fn select_adjacent_heap(&mut self, delta: i32) {
+        let heaps_count = self.nim_game.core().get_heaps_count();
+        
+        if heaps_count == 0 {
+            return;
+        }
+        
+        let heap_index = match &self.target_selection {
+            Some(selection) => (selection.heap_index as i64 + delta as i64).clamp(0, heaps_count as i64 - 1) as usize,
+            None => 0
+        };
+        let heap_count = self.nim_game.core().get_heap_count(heap_index).unwrap_or(0);
+        
+        self.target_selection = Some(TargetSelection {
+            heap_index,
+            target_remaining: heap_count.saturating_sub(1)
+        });
+    }
+    
+    fn commit_target_selection(&mut self) {
+        if !self.is_human_to_move() {
+            return;
+        }
+        
+        if let Some(selection) = self.target_selection.take() {
+            let nim_move_option = self.nim_game.core()
+                .prepare_move_to_remaining(selection.heap_index, selection.target_remaining);
+            
+            if let Some(nim_move) = nim_move_option {
+                self.make_human_move(nim_move);
+            }
+        }
+    }
+    
+    fn handle_ai_move(&mut self) {
+        if !self.is_computer_to_move() {
+            return;
+        }
+        
+        let made = if self.nim_game.core().get_variant() == NimVariant::Wythoff {
+            self.nim_game.prepare_wythoff_ai_move()
+                .is_some_and(|wythoff_move| self.nim_game.make_wythoff_move(&wythoff_move))
+        } else if self.nim_game.core().get_variant() == NimVariant::Staircase {
+            self.nim_game.prepare_staircase_ai_move()
+                .is_some_and(|staircase_move| self.nim_game.make_staircase_move(&staircase_move))
+        } else if self.nim_game.core().get_max_heaps_per_move() > 1 {
+            self.nim_game.prepare_moore_ai_move()
+                .is_some_and(|moore_move| self.nim_game.make_moore_move(&moore_move))
+        } else {
+            self.nim_game.prepare_ai_move()
+                .is_some_and(|nim_move| self.nim_game.make_move(nim_move).is_ok())
+        };
+        
+        if made {
+            self.audio.play_move_sound();
+            self.last_move_time = self.game_clock;
+            self.broadcast_state();
+        }
+    }
+    
+    fn move_mouse_states(&mut self) {
+        self.previous_mouse_state = self.current_mouse_state.clone();
+    }
+    
+    fn handle_window_size_change(&mut self) {
+        match self.canvas.output_size() {
+            Ok(window_size) => {
+                if !self.fullscreen {
+                    self.settings.window_width = window_size.0;
+                    self.settings.window_height = window_size.1;
+                }
+                
+                self.nim_game.layout(window_size);
+            },
+            Err(e) => println!("Failed to read window size: {}", e)
+        }
+    }
+    
+    fn toggle_fullscreen(&mut self) {
+        let fullscreen_type = if self.fullscreen {
+            FullscreenType::Off
+        } else {
+            FullscreenType::Desktop
+        };
+        
+        match self.canvas.window_mut().set_fullscreen(fullscreen_type) {
+            Ok(()) => {
+                self.fullscreen = !self.fullscreen;
+                self.handle_window_size_change();
+            },
+            Err(e) => println!("Failed to toggle fullscreen: {}", e)
+        }
+    }
+    
+    fn handle_potential_window_resized(&mut self, event: &Event) {
+        if let Event::Window { win_event: WindowEvent::Resized(..) | WindowEvent::SizeChanged(..), .. } = event {
+            self.handle_window_size_change();
+        }
+    }
+    
+    fn handle_potential_mouse_moved(&mut self, event: &Event) {
+        if let Event::MouseMotion { x, y, .. } = event {
+            self.current_mouse_state.point = Point::new(*x, *y);
+            
+            if !self.drag_path.is_empty() {
+                self.drag_path.push(self.current_mouse_state.point);
+            }
+        }
+    }
+    
+    /// The removals of the drag in progress, one per heap dragged across.
+    fn get_drag_move(&self) -> Vec<NimMove> {
+        if self.settings.input_mode != InputMode::Drag || !self.is_human_to_move() {
+            return Vec::new();
+        }
+        
+        self.nim_game.prepare_drag_move(&self.drag_path, self.nim_game.core().get_max_heaps_per_move())
+    }
+    
+    fn is_released_over_pressed_heap(&self) -> bool {
+        let press_point = match self.left_press_point {
+            Some(press_point) => press_point,
+            None => return false
+        };
+        
+        let pressed_heap = self.nim_game.get_heap_index_at(press_point);
+        let released_heap = self.nim_game.get_heap_index_at(self.current_mouse_state.point);
+        
+        pressed_heap.is_some() && pressed_heap == released_heap
+    }
+    
+    fn handle_left_click_down(&mut self) {
+        self.left_press_point = Some(self.current_mouse_state.point);
+        
+        if self.settings.input_mode == InputMode::Drag {
+            self.drag_path = vec![self.current_mouse_state.point];
+        }
+    }
+    
+    fn handle_left_click_up(&mut self) {
+        let released_over_pressed_heap = self.is_released_over_pressed_heap();
+        self.left_press_point = None;
+        let drag_path = std::mem::take(&mut self.drag_path);
+        
+        if !self.accepts_input_from(InputDevice::Mouse) {
+            return;
+        }
+        
+        if self.settings.input_mode == InputMode::Drag {
+            self.handle_player_move(&drag_path);
+            return;
+        }
+        
+        if self.settings.release_over_same_heap && !released_over_pressed_heap {
+            return;
+        }
+        
+        let now = Instant::now();
+        let heap_index = self.nim_game.get_heap_index_at(self.current_mouse_state.point);
+        
+        if let (Some(last_click), Some(heap_index)) = (self.last_click.take(), heap_index) {
+            let previous_click = (last_click.time, last_click.heap_index);
+            
+            if Self::is_double_click(previous_click, (now, heap_index), self.settings.double_click_time) {
+                self.empty_heap_on_double_click(&last_click);
+                return;
+            }
+        }
+        
+        let moves_count_before = self.nim_game.core().get_moves_count();
+        self.handle_player_move(&drag_path);
+        
+        self.last_click = heap_index.map(|heap_index| LastClick {
+            time: now,
+            heap_index,
+            moves_count_before,
+            moves_count_after: self.nim_game.core().get_moves_count()
+        });
+    }
+    
+    fn is_double_click(previous_click: (Instant, usize), click: (Instant, usize), double_click_time: Duration) -> bool {
+        let (previous_time, previous_heap_index) = previous_click;
+        let (time, heap_index) = click;
+        
+        previous_heap_index == heap_index && time.saturating_duration_since(previous_time) <= double_click_time
+    }
+    
+    /// The first click of a double-click may already have taken stones from
+    /// the heap; that move is taken back so the whole heap goes in one move.
+    fn empty_heap_on_double_click(&mut self, first_click: &LastClick) {
+        let first_click_moved = first_click.moves_count_after > first_click.moves_count_before;
+        
+        if first_click_moved && self.nim_game.core().get_moves_count() == first_click.moves_count_after {
+            self.nim_game.undo_last_move();
+        }
+        
+        if !self.is_human_to_move() {
+            return;
+        }
+        
+        let heap_count = self.nim_game.core().get_heap_count(first_click.heap_index).unwrap_or(0);
+        
+        if heap_count > 0 {
+            self.target_selection = None;
+            self.make_human_move(NimMove {
+                heap_index: first_click.heap_index,
+                count_to_remove: heap_count
+            });
+        }
+    }
+    
+    fn handle_right_click_up(&mut self) {
+        if !self.is_human_to_move() || !self.accepts_input_from(InputDevice::Mouse) {
+            return;
+        }
+        
+        if let Some(nim_move) = self.nim_game.prepare_single_stone_move(self.current_mouse_state.point) {
+            self.target_selection = None;
+            self.make_human_move(nim_move);
+        }
+    }
+    
+    fn handle_potential_mouse_button(&mut self, event: &Event) {
+        match event {
+            Event::MouseButtonDown {..} | Event::MouseButtonUp {..} => {
+                self.move_mouse_states();
+                
+                match event {
+                    Event::MouseButtonDown { mouse_btn, .. } => {
+                        match mouse_btn {
+                            sdl2::mouse::MouseButton::Left => {
+                                self.current_mouse_state.left_button = true;
+                                self.handle_left_click_down();
+                            },
+                            sdl2::mouse::MouseButton::Right => self.current_mouse_state.right_button = true,
+                            _ => {}
+                        }
+                    },
+                    Event::MouseButtonUp { mouse_btn, .. } => {
+                        match mouse_btn {
+                            sdl2::mouse::MouseButton::Left => {
+                                self.current_mouse_state.left_button = false;
+                                self.handle_left_click_up();
+                            },
+                            sdl2::mouse::MouseButton::Right => {
+                                self.current_mouse_state.right_button = false;
+                                self.handle_right_click_up();
+                            },
+                            _ => {}
+                        }
+                    },
+                    _ => {}
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn handle_potential_mouse_wheel(&mut self, event: &Event) {
+        if !self.accepts_input_from(InputDevice::Mouse) {
+            return;
+        }
+        
+        if let Event::MouseWheel { y, .. } = event {
+            self.adjust_target_remaining(*y);
+        }
+    }
+    
+    fn handle_potential_shift_key(&mut self, event: &Event) {
+        match event {
+            Event::KeyDown { keycode: Some(Keycode::LShift | Keycode::RShift), .. } => self.shift_held = true,
+            Event::KeyUp { keycode: Some(Keycode::LShift | Keycode::RShift), .. } => self.shift_held = false,
+            _ => {}
+        }
+    }
+    
+    fn handle_potential_key_down(&mut self, event: &Event) {
+        if let Event::KeyDown { keycode: Some(keycode), repeat, .. } = event {
+            let keyboard_input = self.accepts_input_from(InputDevice::Keyboard);
+            
+            match keycode {
+                Keycode::Left if keyboard_input => self.select_adjacent_heap(-1),
+                Keycode::Right if keyboard_input => self.select_adjacent_heap(1),
+                Keycode::Up if keyboard_input => self.adjust_target_remaining(1),
+                Keycode::Down if keyboard_input => self.adjust_target_remaining(-1),
+                Keycode::Return if !repeat => self.commit_selected_move(),
+                Keycode::A if !repeat => self.add_human_stone(),
+                Keycode::C if !repeat => self.swap_sides(),
+                Keycode::U if !repeat => self.undo_move(),
+                Keycode::Y if !repeat => self.redo_move(),
+                Keycode::S if !repeat => self.save_game(),
+                Keycode::L if !repeat => self.load_game(),
+                Keycode::N if !repeat => self.start_next_lesson(),
+                Keycode::R if !repeat => self.reset_game(),
+                Keycode::End if !repeat => self.fast_forward_to_result(),
+                Keycode::Space if !repeat => self.toggle_pause(),
+                Keycode::H if !repeat => self.toggle_hint(),
+                Keycode::F if !repeat => self.toggle_fullscreen(),
+                Keycode::B if !repeat => self.show_binary_breakdown = !self.show_binary_breakdown,
+                Keycode::F3 if !repeat => self.show_debug_overlay = !self.show_debug_overlay,
+                _ => {}
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: Event) -> GameEvent {
+        match event {
+            Event::Quit {..} |
+            Event::KeyDown { keycode: Some(Keycode::Escape), .. } => GameEvent::Quit,
+            _ if self.state == GameState::GameOver => {
+                self.handle_potential_window_resized(&event);
+                
+                if let Event::KeyDown { keycode: Some(Keycode::Return), repeat: false, .. } = event {
+                    self.play_again();
+                }
+                
+                GameEvent::Other(event)
+            },
+            _ if self.takeback_offered => {
+                self.handle_potential_window_resized(&event);
+                self.handle_potential_mouse_moved(&event);
+                self.handle_potential_takeback_key_down(&event);
+                
+                GameEvent::Other(event)
+            },
+            _ if self.is_replaying() => {
+                self.handle_potential_window_resized(&event);
+                self.handle_potential_mouse_moved(&event);
+                self.handle_potential_replay_key_down(&event);
+                
+                GameEvent::Other(event)
+            },
+            _ => {
+                self.handle_potential_window_resized(&event);
+                self.handle_potential_mouse_moved(&event);
+                self.handle_potential_mouse_button(&event);
+                self.handle_potential_mouse_wheel(&event);
+                self.handle_potential_shift_key(&event);
+                self.handle_potential_key_down(&event);
+                
+                GameEvent::Other(event)
+            }
+        }
+    }
+    
+    fn draw_background(&mut self) -> Result<(), String> {
+        let current_player = self.nim_game.core().get_player_to_move();
+        let target_colour = self.settings.theme.get_player_background(current_player);
+        
+        let ratio = transition_ratio(self.frame_time, self.settings.target_colour_change_time);
+        
+        self.background_colour = step_colour(self.background_colour, target_colour, ratio);
+        
+        match &self.background_texture {
+            Some(texture) => {
+                let tint_alpha = 80;
+                let tint_colour = Color::RGBA(
+                    self.background_colour.r,
+                    self.background_colour.g,
+                    self.background_colour.b,
+                    tint_alpha
+                );
+                
+                self.canvas.copy(texture, None, None)?;
+                self.canvas.set_blend_mode(BlendMode::Blend);
+                self.canvas.set_draw_color(tint_colour);
+                self.canvas.fill_rect(None)?;
+                self.canvas.set_blend_mode(BlendMode::None);
+            },
+            None => {
+                self.canvas.set_draw_color(self.background_colour);
+                self.canvas.clear();
+            }
+        }
+        
+        Ok(())
+    }
+
+    fn draw_frame(&mut self) -> Result<(), GameError> {
+        self.draw_background().map_err(GameError::Render)?;
+        self.nim_game.draw_board(&mut self.canvas, &self.current_mouse_state)?;
+        self.draw_overlays().map_err(GameError::Render)?;
+        self.canvas.present();
+        
+        Ok(())
+    }
+    
+    /// Everything drawn on top of the board.
+    fn draw_overlays(&mut self) -> Result<(), String> {
+        self.draw_turn_banner()?;
+        self.nim_game.draw_count_labels(&mut self.canvas, &self.text_renderer, self.settings.heap_label_colour)?;
+        
+        if self.settings.show_heap_indices {
+            self.nim_game.draw_index_labels(&mut self.canvas, &self.text_renderer, self.settings.heap_label_colour)?;
+        }
+        
+        if let Some(selection) = &self.target_selection {
+            self.nim_game.draw_heap_highlight(&mut self.canvas, selection.heap_index)?;
+            
+            let nim_move_option = self.nim_game.core()
+                .prepare_move_to_remaining(selection.heap_index, selection.target_remaining);
+            
+            if let Some(nim_move) = nim_move_option {
+                self.nim_game.draw_move_preview(&mut self.canvas, &nim_move)?;
+            }
+        }
+        
+        for drag_move in self.get_drag_move() {
+            self.nim_game.draw_heap_highlight(&mut self.canvas, drag_move.heap_index)?;
+            self.nim_game.draw_move_preview(&mut self.canvas, &drag_move)?;
+        }
+        
+        if let Some(pending_move) = &self.pending_move {
+            self.nim_game.draw_heap_highlight(&mut self.canvas, pending_move.heap_index)?;
+            self.nim_game.draw_move_preview(&mut self.canvas, pending_move)?;
+        }
+        
+        for removal in &self.moore_selection {
+            self.nim_game.draw_heap_highlight(&mut self.canvas, removal.heap_index)?;
+            self.nim_game.draw_move_preview(&mut self.canvas, removal)?;
+        }
+        
+        if self.show_hint && self.is_human_to_move() {
+            self.draw_hint()?;
+        }
+        
+        self.draw_status_text()?;
+        self.draw_replay_status()?;
+        
+        if self.takeback_offered {
+            self.draw_takeback_prompt()?;
+        }
+        
+        if !self.nim_game.is_board_visible() {
+            self.draw_window_too_small()?;
+        }
+        
+        self.draw_scoreboard()?;
+        self.draw_turn_timer()?;
+        self.draw_ai_thinking_indicator()?;
+        
+        if self.settings.show_fps {
+            self.draw_fps_counter()?;
+        }
+        
+        if self.state == GameState::Playing {
+            self.draw_heap_tooltip()?;
+        }
+        
+        if self.settings.show_nim_value {
+            self.draw_nim_value()?;
+        }
+        
+        if self.settings.show_starting_outcome {
+            self.draw_starting_outcome()?;
+        }
+        
+        if self.show_binary_breakdown {
+            self.draw_binary_breakdown()?;
+        }
+        
+        if self.is_paused() {
+            self.draw_pause_overlay()?;
+        }
+        
+        if self.show_debug_overlay {
+            self.draw_debug_overlay()?;
+        }
+
+        Ok(())
+    }
+
+    fn draw_status_text(&mut self) -> Result<(), String> {
+        let window_size = self.canvas.output_size()?;
+        let text_colour = Color::RGB(255, 255, 255);
+        
+        match self.nim_game.core().get_result() {
+            Some(result) => {
+                let banner_text = match (self.get_match_winner(), result) {
+                    (Some(match_winner), _) => format!("{} wins the match!", match_winner),
+                    (None, GameResult::Win(winner)) => format!("{} wins!", winner),
+                    (None, GameResult::Draw) => "It's a draw!".to_string()
+                };
+                let banner_area = Rect::new(0, 0, window_size.0, window_size.1);
+                let prompt_area = Rect::new(0, window_size.1 as i32 - MARGIN_TOP as i32, window_size.0, MARGIN_TOP);
+                
+                self.text_renderer.draw_banner(&mut self.canvas, &banner_text, text_colour, banner_area)?;
+                
+                if self.state == GameState::GameOver {
+                    self.text_renderer.draw_centered(&mut self.canvas, "Press Enter to play again or Escape to quit", text_colour, prompt_area)?;
+                }
+                
+                Ok(())
+            },
+            None => {
+                let player_to_move = self.nim_game.core().get_player_to_move();
+                let status_text = match self.players.get(player_to_move) {
+                    Some(player_type) => format!("{} ({}) to move", player_to_move, player_type),
+                    None => format!("{} to move", player_to_move)
+                };
+                let status_area = Self::turn_banner_area(window_size);
+                
+                self.text_renderer.draw_centered(&mut self.canvas, &status_text, text_colour, status_area)
+            }
+        }
+    }
+
+    fn draw_takeback_prompt(&mut self) -> Result<(), String> {
+        let window_size = self.canvas.output_size()?;
+        let prompt_area = Rect::new(0, window_size.1 as i32 - MARGIN_TOP as i32, window_size.0, MARGIN_TOP);
+        let prompt_text = "That move gives away the win: U to take it back, Enter to keep it";
+        
+        self.text_renderer.draw_centered(&mut self.canvas, prompt_text, Color::RGB(255, 200, 200), prompt_area)
+    }
+
+    fn draw_window_too_small(&mut self) -> Result<(), String> {
+        let window_size = self.canvas.output_size()?;
+        let message_area = Rect::new(0, 0, window_size.0, window_size.1);
+        
+        self.text_renderer.draw_label(&mut self.canvas, "Window too small to draw the board", Color::RGB(255, 255, 255), message_area)
+    }
+
+    fn draw_replay_status(&mut self) -> Result<(), String> {
+        let replay_viewer = match &self.replay_viewer {
+            Some(replay_viewer) => replay_viewer,
+            None => return Ok(())
+        };
+        
+        let window_size = self.canvas.output_size()?;
+        let replay_text = format!(
+            "Replay: game {}/{}, move {}/{} (arrows to step)",
+            replay_viewer.get_game_index() + 1,
+            replay_viewer.get_games_count(),
+            replay_viewer.get_move_index(),
+            replay_viewer.get_moves_count()
+        );
+        let replay_area = Rect::new(0, window_size.1 as i32 - LABEL_HEIGHT as i32, window_size.0, LABEL_HEIGHT);
+        
+        self.text_renderer.draw_label(&mut self.canvas, &replay_text, Color::RGB(255, 255, 255), replay_area)
+    }
+    
+    /// The band above the board, across the whole window width.
+    fn turn_banner_area(window_size: (u32, u32)) -> Rect {
+        Rect::new(0, 0, window_size.0, MARGIN_TOP.min(window_size.1))
+    }
+    
+    /// Fills the banner with the player to move's own background colour and
+    /// separates it from the board with a line along its bottom edge. Drawn
+    /// before the other overlays, as the heap index and hint labels sit in it.
+    fn draw_turn_banner(&mut self) -> Result<(), String> {
+        if self.nim_game.core().get_result().is_some() {
+            return Ok(());
+        }
+        
+        let banner_area = Self::turn_banner_area(self.canvas.output_size()?);
+        let player_to_move = self.nim_game.core().get_player_to_move();
+        
+        self.canvas.set_draw_color(self.settings.theme.get_player_background(player_to_move));
+        self.canvas.fill_rect(banner_area)?;
+        
+        self.canvas.set_draw_color(self.settings.theme.board_border);
+        self.canvas.draw_line(banner_area.bottom_left() - Point::new(0, 1), banner_area.bottom_right() - Point::new(1, 1))
+    }
+    
+    fn draw_hint(&mut self) -> Result<(), String> {
+        match self.nim_game.core().prepare_hint() {
+            Some(removals) => {
+                let pulse_colour = step_colour(Color::RGB(120, 100, 0), Color::RGB(255, 220, 0), self.hint_pulse());
+                
+                for nim_move in &removals {
+                    self.nim_game.draw_heap_outline(&mut self.canvas, nim_move.heap_index, pulse_colour)?;
+                    self.nim_game.draw_move_preview(&mut self.canvas, nim_move)?;
+                }
+                
+                Ok(())
+            },
+            None => {
+                let window_size = self.canvas.output_size()?;
+                let hint_area = Rect::new(window_size.0 as i32 - 200, 0, 200, LABEL_HEIGHT);
+                
+                self.text_renderer.draw_label(&mut self.canvas, "no winning move", Color::RGB(255, 200, 200), hint_area)
+            }
+        }
+    }
+    
+    /// The player to move at the start of a game and whether they can force
+    /// a win from there.
+    fn get_starting_outcome(nim_game: &NimGame) -> (Player, bool) {
+        let core = nim_game.core();
+        
+        (core.get_player_to_move().clone(), core.is_winning_position())
+    }
+    
+    fn draw_starting_outcome(&mut self) -> Result<(), String> {
+        let (player, winning) = &self.starting_outcome;
+        let (outcome, colour) = if *winning {
+            ("won", Color::RGB(150, 255, 150))
+        } else {
+            ("lost", Color::RGB(255, 150, 150))
+        };
+        let outcome_text = format!("Start {} for {}", outcome, player);
+        
+        let window_size = self.canvas.output_size()?;
+        let outcome_area = Rect::new(window_size.0 as i32 - 200, 2 * LABEL_HEIGHT as i32, 200, LABEL_HEIGHT);
+        
+        self.text_renderer.draw_label(&mut self.canvas, &outcome_text, colour, outcome_area)
+    }
+    
+    fn format_binary(count: u32) -> String {
+        format!("{:b}", count)
+    }
+    
+    /// The hovered heap's count in decimal and binary, next to the cursor but
+    /// kept inside the window.
+    fn draw_heap_tooltip(&mut self) -> Result<(), String> {
+        let point = self.current_mouse_state.point;
+        let heap_count = match self.nim_game.get_heap_index_at(point)
+            .and_then(|heap_index| self.nim_game.core().get_heap_count(heap_index)) {
+            Some(heap_count) => heap_count,
+            None => return Ok(())
+        };
+        
+        let tooltip_text = format!("{} = {}b", heap_count, Self::format_binary(heap_count));
+        let tooltip_width = 140;
+        let cursor_offset = 16;
+        
+        let window_size = self.canvas.output_size()?;
+        let x = (point.x() + cursor_offset).min(window_size.0 as i32 - tooltip_width as i32).max(0);
+        let y = (point.y() + cursor_offset).min(window_size.1 as i32 - LABEL_HEIGHT as i32).max(0);
+        let tooltip_area = Rect::new(x, y, tooltip_width, LABEL_HEIGHT);
+        
+        self.canvas.set_draw_color(Color::RGB(30, 30, 30));
+        self.canvas.fill_rect(tooltip_area)?;
+        self.canvas.set_draw_color(Color::RGB(200, 200, 200));
+        self.canvas.draw_rect(tooltip_area)?;
+        
+        self.text_renderer.draw_label(&mut self.canvas, &tooltip_text, Color::RGB(255, 255, 255), tooltip_area)
+    }
+    
+    fn draw_nim_value(&mut self) -> Result<(), String> {
+        let position = if self.nim_game.core().is_winning_position() { "winning" } else { "losing" };
+        let nim_value_text = format!("Nim-sum {} ({})", self.nim_game.core().nim_value(), position);
+        
+        let window_size = self.canvas.output_size()?;
+        let nim_value_area = Rect::new(window_size.0 as i32 - 200, LABEL_HEIGHT as i32, 200, LABEL_HEIGHT);
+        
+        self.text_renderer.draw_label(&mut self.canvas, &nim_value_text, Color::RGB(255, 255, 0), nim_value_area)
+    }
+    
+    fn draw_binary_breakdown(&mut self) -> Result<(), String> {
+        fn format_bits(bits: &[bool]) -> String {
+            bits.iter().map(|&bit| if bit { '1' } else { '0' }).collect()
+        }
+        
+        let breakdown = self.nim_game.binary_breakdown();
+        let row_width = 160;
+        
+        for (heap_index, row) in breakdown.heap_rows.iter().enumerate() {
+            let row_text = format!("{}: {}", heap_index + 1, format_bits(row));
+            let row_area = Rect::new(0, ((heap_index + 1) as u32 * LABEL_HEIGHT) as i32, row_width, LABEL_HEIGHT);
+            
+            self.text_renderer.draw_label(&mut self.canvas, &row_text, Color::RGB(255, 255, 255), row_area)?;
+        }
+        
+        let xor_text = format!("XOR: {}", format_bits(&breakdown.xor_row));
+        let xor_y = ((breakdown.heap_rows.len() + 1) as u32 * LABEL_HEIGHT) as i32;
+        let xor_colour = if breakdown.xor_row.contains(&true) {
+            Color::RGB(255, 120, 120)
+        } else {
+            Color::RGB(120, 255, 120)
+        };
+        
+        self.text_renderer.draw_label(&mut self.canvas, &xor_text, xor_colour, Rect::new(0, xor_y, row_width, LABEL_HEIGHT))
+    }
+    
+    fn draw_fps_counter(&mut self) -> Result<(), String> {
+        let frame_time = match self.smoothed_frame_time {
+            Some(frame_time) if frame_time > 0.0 => frame_time,
+            _ => return Ok(())
+        };
+        
+        let window_size = self.canvas.output_size()?;
+        let fps_text = format!("{:.1} FPS ({:.1} ms)", 1.0 / frame_time, frame_time * 1000.0);
+        let fps_area = Rect::new(0, window_size.1 as i32 - LABEL_HEIGHT as i32, 200, LABEL_HEIGHT);
+        
+        self.text_renderer.draw_label(&mut self.canvas, &fps_text, Color::RGB(255, 255, 0), fps_area)
+    }
+    
+    fn draw_pause_overlay(&mut self) -> Result<(), String> {
+        let window_size = self.canvas.output_size()?;
+        let window_area = Rect::new(0, 0, window_size.0, window_size.1);
+        
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(Color::RGBA(0, 0, 0, 150));
+        self.canvas.fill_rect(window_area)?;
+        self.canvas.set_blend_mode(BlendMode::None);
+        
+        self.text_renderer.draw_banner(&mut self.canvas, "PAUSED", Color::RGB(255, 255, 255), window_area)
+    }
+    
+    fn draw_scoreboard(&mut self) -> Result<(), String> {
+        let score_text = Player::all(self.settings.players_count)
+            .map(|player| self.scoreboard.get_wins(&player).to_string())
+            .collect::<Vec<String>>()
+            .join(" - ");
+        let (score_text, score_width) = match self.scoreboard.get_draws() {
+            0 => (score_text, 60 * self.settings.players_count),
+            draws => (format!("{} ({} drawn)", score_text, draws), 60 * self.settings.players_count + 100)
+        };
+        let score_area = Rect::new(0, 0, score_width, LABEL_HEIGHT);
+        
+        self.text_renderer.draw_label(&mut self.canvas, &score_text, Color::RGB(255, 255, 255), score_area)
+    }
+    
+    fn draw_turn_timer(&mut self) -> Result<(), String> {
+        let remaining_time = match self.remaining_turn_time() {
+            Some(remaining_time) => remaining_time,
+            None => return Ok(())
+        };
+        let turn_time_limit = match self.settings.turn_time_limit {
+            Some(turn_time_limit) if !turn_time_limit.is_zero() => turn_time_limit,
+            _ => return Ok(())
+        };
+        
+        let window_size = self.canvas.output_size()?;
+        let fraction = remaining_time.as_secs_f64() / turn_time_limit.as_secs_f64();
+        let bar_width = (window_size.0 as f64 * fraction) as u32;
+        
+        if bar_width == 0 {
+            return Ok(());
+        }
+        
+        self.canvas.set_draw_color(Color::RGB(220, 180, 60));
+        self.canvas.fill_rect(Rect::new(0, MARGIN_TOP as i32 - 8, bar_width, 4))
+    }
+
+    fn draw_ai_thinking_indicator(&mut self) -> Result<(), String> {
+        let progress = match self.ai_thinking_progress() {
+            Some(progress) => progress,
+            None => return Ok(())
+        };
+        
+        let window_size = self.canvas.output_size()?;
+        let bar_width = (window_size.0 as f64 * progress) as u32;
+        
+        if bar_width == 0 {
+            return Ok(());
+        }
+        
+        self.canvas.set_draw_color(Color::RGB(90, 160, 220));
+        self.canvas.fill_rect(Rect::new(0, MARGIN_TOP as i32 - 8, bar_width, 4))
+    }
+
+    fn draw_debug_overlay(&mut self) -> Result<(), String> {
+        let point = self.current_mouse_state.point;
+        let hovered_heap = match self.nim_game.get_heap_index_at(point) {
+            Some(heap_index) => format!("heap {}", heap_index),
+            None => "no heap".to_string()
+        };
+        let debug_text = format!("mouse {}, {} | {} | {} frame overruns",
+            point.x(), point.y(), hovered_heap, self.frame_overruns);
+        let window_size = self.canvas.output_size()?;
+        let debug_area = Rect::new(0, window_size.1 as i32 - 2 * LABEL_HEIGHT as i32, window_size.0, LABEL_HEIGHT);
+        
+        self.nim_game.draw_debug_overlay(&mut self.canvas, &self.current_mouse_state)?;
+        self.text_renderer.draw_label(&mut self.canvas, &debug_text, Color::RGB(255, 255, 0), debug_area)
+    }
+
+    /// Caps the time step of a frame at `max_catchup_frames` frame budgets, so
+    /// a stall (e.g. a window drag) doesn't make animations, the background or
+    /// the AI delay jump ahead all at once. Only unpaused frames advance the
+    /// game clock.
+    /// Frame times run from one frame's start to the next, so they include
+    /// the update, the drawing and the wait in between.
+    fn begin_frame(&mut self) {
+        let now = Instant::now();
+        let elapsed_time = now.saturating_duration_since(self.last_frame_time);
+        self.last_frame_time = now;
+        self.update_frame_time(elapsed_time);
+        
+        let max_frame_time = self.frame_budget * self.settings.max_catchup_frames.max(1);
+        
+        self.frame_time = elapsed_time.min(max_frame_time);
+        
+        if elapsed_time > max_frame_time {
+            self.frame_overruns += 1;
+        }
+        
+        if !self.is_paused() {
+            self.game_clock += self.frame_time;
+        }
+    }
+    
+    fn remaining_frame_time(frame_budget: Duration, elapsed_time: Duration) -> Option<Duration> {
+        frame_budget.checked_sub(elapsed_time)
+            .filter(|remaining_time| !remaining_time.is_zero())
+    }
+
+    /// Whether something on screen changes without input: the computer
+    /// thinking, a turn timer, a removal animation or the background fade.
+    fn is_work_pending(&self) -> bool {
+        let player_to_move = self.nim_game.core().get_player_to_move();
+        let background_settled = self.background_colour == self.settings.theme.get_player_background(player_to_move);
+        let turn_running = self.state == GameState::Playing && !self.is_paused() && !self.is_replaying()
+            && (self.is_computer_to_move() || self.remaining_turn_time().is_some());
+        
+        turn_running || self.show_hint || self.nim_game.is_animating() || !background_settled
+    }
+    
+    fn is_idle(time_since_input: Duration, work_pending: bool) -> bool {
+        !work_pending && time_since_input >= IDLE_DELAY
+    }
+    
+    /// Frames stretch to `idle_microseconds_per_frame`, when set, once the
+    /// game has been idle for a while, to save CPU.
+    fn get_frame_budget(&self) -> Duration {
+        let frame_budget = Duration::from_micros(self.settings.microseconds_per_frame);
+        
+        match self.settings.idle_microseconds_per_frame {
+            Some(idle_microseconds) if Self::is_idle(self.last_input_time.elapsed(), self.is_work_pending()) =>
+                frame_budget.max(Duration::from_micros(idle_microseconds)),
+            _ => frame_budget
+        }
+    }
+    
+    fn wait_to_next_frame(&mut self, start_time: Instant) {
+        self.frame_budget = self.get_frame_budget();
+        
+        let frame_budget = self.frame_budget;
+
+        if let Some(remaining_duration) = Self::remaining_frame_time(frame_budget, start_time.elapsed()) {
+            let deadline = Instant::now() + remaining_duration;
+            
+            match self.settings.frame_pacing {
+                FramePacing::Sleep => ::std::thread::sleep(remaining_duration),
+                FramePacing::Spin => Self::spin_until(deadline),
+                FramePacing::Hybrid => {
+                    let spin_duration = Duration::from_millis(2);
+                    
+                    ::std::thread::sleep(remaining_duration.saturating_sub(spin_duration));
+                    Self::spin_until(deadline);
+                }
+            }
+        }
+    }
+    
+    fn spin_until(deadline: Instant) {
+        while Instant::now() < deadline {
+            ::std::hint::spin_loop();
+        }
+    }
+}
+
+/// Prints every position of every game in a move log, one move at a time.
+pub fn print_replay(path: &Path, settings: &GameSettings) -> Result<(), String> {
+    let recorded_games = MoveRecorder::load(path)?;
+    
+    for (game_index, recorded_game) in recorded_games.iter().enumerate() {
+        let default_heap = NimHeap::new(settings.max_stones_per_heap, 10);
+        let mut nim_game = NimGame::from_recording(default_heap, recorded_game)?;
+        
+        println!("Game {}", game_index + 1);
+        println!("{}", nim_game);
+        
+        for (move_index, recorded_move) in recorded_game.moves.iter().enumerate() {
+            if !nim_game.replay_move(recorded_move) {
+                return Err(format!("Illegal move {} in game {}", move_index + 1, game_index + 1));
+            }
+            
+            println!("{}", nim_game);
+        }
+    }
+    
+    Ok(())
+}
+
+/// Plays `games_count` games without a window, the AI as configured for
+/// the chosen variant against random moves, and prints how often each side
+/// won. Each board is drawn from its own seed, taken from the settings' one.
+pub fn print_simulation(games_count: u32, settings: &GameSettings) {
+    let mut rng = StdRng::seed_from_u64(settings.seed.unwrap_or_else(rand::random::<u64>));
+    let random_strategy = RandomStrategy::new(settings.max_removal_per_move);
+    let heaps_count = match settings.variant {
+        NimVariant::Wythoff => 2,
+        NimVariant::Fibonacci => 1,
+        _ => settings.heaps_count
+    };
+    let mut scoreboard = Scoreboard::new();
+    let mut total_stones = 0;
+    
+    for _ in 0..games_count {
+        let mut nim_game = NimGame::new_random(heaps_count as usize, 1, settings.max_stones_per_heap);
+        Game::configure_nim_game(&mut nim_game, settings);
+        
+        // Deal the board again from its own seed; the first one isn't seeded.
+        nim_game.set_seed(rng.next_u64());
+        nim_game.reset();
+        total_stones += nim_game.total_stones() as u64;
+        
+        let result = nim_game.simulate(&random_strategy, &mut rng);
+        scoreboard.record(&result);
+    }
+    
+    println!("Simulated {} games, {:.1} stones per board on average",
+        games_count, total_stones as f64 / games_count.max(1) as f64);
+    println!("{} (configured strategy): {} wins", Player::new(0), scoreboard.get_wins(&Player::new(0)));
+    println!("{} (random moves): {} wins", Player::new(1), scoreboard.get_wins(&Player::new(1)));
+    println!("Draws: {}", scoreboard.get_draws());
+}
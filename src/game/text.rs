@@ -0,0 +1,105 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{TextureCreator, WindowCanvas};
+use sdl2::ttf::Font;
+use sdl2::video::WindowContext;
+
+/// Scales a rendered text size down, keeping its aspect ratio, until it fits
+/// inside `area_size`. Text that already fits keeps its size.
+fn fit_text_size(text_size: (u32, u32), area_size: (u32, u32)) -> (u32, u32) {
+    let (width, height) = text_size;
+    let (area_width, area_height) = area_size;
+    
+    if width <= area_width && height <= area_height {
+        return text_size;
+    }
+    
+    let scale = (area_width as f64 / width.max(1) as f64).min(area_height as f64 / height.max(1) as f64);
+    
+    (((width as f64 * scale) as u32).max(1), ((height as f64 * scale) as u32).max(1))
+}
+
+struct Fonts {
+    font: Font<'static, 'static>,
+    banner_font: Font<'static, 'static>,
+    label_font: Font<'static, 'static>,
+}
+
+/// Without fonts every draw call succeeds without drawing anything, so the
+/// game stays playable when the font file is missing.
+pub struct TextRenderer {
+    texture_creator: TextureCreator<WindowContext>,
+    fonts: Option<Fonts>,
+}
+
+impl TextRenderer {
+    pub fn new(
+        texture_creator: TextureCreator<WindowContext>,
+        font: Font<'static, 'static>,
+        banner_font: Font<'static, 'static>,
+        label_font: Font<'static, 'static>
+    ) -> TextRenderer {
+        TextRenderer {
+            texture_creator,
+            fonts: Some(Fonts {
+                font,
+                banner_font,
+                label_font,
+            }),
+        }
+    }
+    
+    pub fn disabled(texture_creator: TextureCreator<WindowContext>) -> TextRenderer {
+        TextRenderer {
+            texture_creator,
+            fonts: None,
+        }
+    }
+    
+    fn draw_with_font(
+        canvas: &mut WindowCanvas,
+        texture_creator: &TextureCreator<WindowContext>,
+        font: &Font,
+        text: &str,
+        colour: Color,
+        area: Rect
+    ) -> Result<(), String> {
+        let surface = font.render(text)
+            .blended(colour)
+            .map_err(|e| e.to_string())?;
+        let texture = texture_creator
+            .create_texture_from_surface(&surface)
+            .map_err(|e| e.to_string())?;
+        
+        let (width, height) = fit_text_size((surface.width(), surface.height()), (area.width(), area.height()));
+        let target = Rect::from_center(area.center(), width, height);
+        let result = canvas.copy(&texture, None, target);
+        
+        unsafe {
+            texture.destroy();
+        }
+        
+        result
+    }
+    
+    pub fn draw_centered(&self, canvas: &mut WindowCanvas, text: &str, colour: Color, area: Rect) -> Result<(), String> {
+        match &self.fonts {
+            Some(fonts) => Self::draw_with_font(canvas, &self.texture_creator, &fonts.font, text, colour, area),
+            None => Ok(())
+        }
+    }
+    
+    pub fn draw_label(&self, canvas: &mut WindowCanvas, text: &str, colour: Color, area: Rect) -> Result<(), String> {
+        match &self.fonts {
+            Some(fonts) => Self::draw_with_font(canvas, &self.texture_creator, &fonts.label_font, text, colour, area),
+            None => Ok(())
+        }
+    }
+    
+    pub fn draw_banner(&self, canvas: &mut WindowCanvas, text: &str, colour: Color, area: Rect) -> Result<(), String> {
+        match &self.fonts {
+            Some(fonts) => Self::draw_with_font(canvas, &self.texture_creator, &fonts.banner_font, text, colour, area),
+            None => Ok(())
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+use serde::Deserialize;
+use sdl2::pixels::Color;
+use super::system::Player;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    Default,
+    Dark
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub player_backgrounds: [Color; 4],
+    pub stone_not_hovered: Color,
+    pub stone_hovered: Color,
+    pub stone_to_remove: Color,
+    pub stone_border: Color,
+    pub empty_slot: Color,
+    pub heap_border: Color,
+    pub board_border: Color,
+}
+
+impl Theme {
+    pub fn from_name(name: ThemeName) -> Theme {
+        match name {
+            ThemeName::Default => Theme {
+                player_backgrounds: [
+                    Color::RGB(100, 155, 0),
+                    Color::RGB(155, 100, 0),
+                    Color::RGB(0, 100, 155),
+                    Color::RGB(120, 0, 140)
+                ],
+                stone_not_hovered: Color::RGB(100, 100, 100),
+                stone_hovered: Color::RGB(140, 110, 110),
+                stone_to_remove: Color::RGB(200, 100, 100),
+                stone_border: Color::RGB(255, 255, 255),
+                empty_slot: Color::RGB(70, 70, 70),
+                heap_border: Color::RGB(0, 0, 0),
+                board_border: Color::RGB(255, 255, 255),
+            },
+            ThemeName::Dark => Theme {
+                player_backgrounds: [
+                    Color::RGB(20, 40, 30),
+                    Color::RGB(40, 25, 20),
+                    Color::RGB(20, 30, 45),
+                    Color::RGB(35, 20, 40)
+                ],
+                stone_not_hovered: Color::RGB(60, 60, 70),
+                stone_hovered: Color::RGB(90, 80, 110),
+                stone_to_remove: Color::RGB(170, 60, 80),
+                stone_border: Color::RGB(140, 140, 150),
+                empty_slot: Color::RGB(45, 45, 50),
+                heap_border: Color::RGB(90, 90, 100),
+                board_border: Color::RGB(120, 120, 130),
+            }
+        }
+    }
+
+    /// Players beyond the last colour reuse the colours from the start.
+    pub fn get_player_background(&self, player: &Player) -> Color {
+        self.player_backgrounds[player.index() as usize % self.player_backgrounds.len()]
+    }
+    
+    /// This theme with the stones of one heap in its own hue, still a bit
+    /// brighter when hovered.
+    pub fn with_heap_hue(&self, heap_index: usize, heaps_count: usize) -> Theme {
+        Theme {
+            stone_not_hovered: heap_colour(heap_index, heaps_count, 0.7),
+            stone_hovered: heap_colour(heap_index, heaps_count, 0.95),
+            ..*self
+        }
+    }
+}
+
+/// Hues evenly spaced around the colour wheel, one for each heap.
+fn heap_colour(heap_index: usize, heaps_count: usize, value: f64) -> Color {
+    let hue = heap_index as f64 / heaps_count.max(1) as f64 * 6.0;
+    let saturation = 0.6;
+    
+    let chroma = value * saturation;
+    let secondary = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, secondary, 0.0),
+        1 => (secondary, chroma, 0.0),
+        2 => (0.0, chroma, secondary),
+        3 => (0.0, secondary, chroma),
+        4 => (secondary, 0.0, chroma),
+        _ => (chroma, 0.0, secondary)
+    };
+    
+    let lightness = value - chroma;
+    let channel = |c: f64| ((c + lightness) * 255.0).round() as u8;
+    
+    Color::RGB(channel(r), channel(g), channel(b))
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::from_name(ThemeName::Default)
+    }
+}
+
+/// Exponential approach: the remaining difference shrinks by a factor of e
+/// every `change_time`, whatever the frame rate.
+pub fn transition_ratio(frame_time: Duration, change_time: Duration) -> f64 {
+    if change_time.is_zero() {
+        1.0
+    } else {
+        1.0 - (-frame_time.as_secs_f64() / change_time.as_secs_f64()).exp()
+    }
+}
+
+fn step_channel(current: u8, target: u8, ratio: f64) -> u8 {
+    let difference = target as f64 - current as f64;
+    let step = difference.signum() * (difference.abs() * ratio).ceil();
+    
+    (current as f64 + step).clamp(0.0, 255.0) as u8
+}
+
+/// Moves each channel `ratio` of the way to the target, at least one step
+/// so the target is always reached.
+pub fn step_colour(current: Color, target: Color, ratio: f64) -> Color {
+    Color::RGB(
+        step_channel(current.r, target.r, ratio),
+        step_channel(current.g, target.g, ratio),
+        step_channel(current.b, target.b, ratio)
+    )
+}
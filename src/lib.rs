@@ -0,0 +1,3 @@
+extern crate sdl2;
+
+pub mod game;
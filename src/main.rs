@@ -1,19 +1,36 @@
-extern crate sdl2;
-
 use std::error::Error;
+use std::path::Path;
 
-mod game;
+use nim::game;
 
 pub fn main() -> Result<(), Box<dyn Error>> {
-    let game_settings = game::system::GameSettings {
-        window_width: 1200,
-        window_height: 800,
-        microseconds_per_frame: 1_000_000 / 60,
-        microseconds_per_ai_move: 1_000_000 / 2, 
-        heaps_count: 25,
-        max_stones_per_heap: 40,
-        target_colour_change_time: std::time::Duration::from_millis(500)
+    let settings_path = Path::new("nim.toml");
+    let mut game_settings = if settings_path.exists() {
+        game::system::GameSettings::from_file(settings_path)?
+    } else {
+        game::system::GameSettings::default()
     };
+    
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    
+    if let Err(e) = game::cli::parse_args(&args, &mut game_settings) {
+        eprintln!("{}", e);
+        eprintln!("{}", game::cli::USAGE);
+        std::process::exit(2);
+    }
+    
+    if let Some(simulated_games) = game_settings.simulated_games {
+        game::system::print_simulation(simulated_games, &game_settings);
+        
+        return Ok(());
+    }
+    
+    if let Some(replay_file) = &game_settings.replay_file {
+        game::system::print_replay(replay_file, &game_settings)?;
+        
+        return Ok(());
+    }
+    
     let mut game = game::system::Game::new(game_settings)?;
 
     game.run()?;